@@ -1,6 +1,7 @@
 #import bevy_pbr::{
     pbr_fragment::pbr_input_from_standard_material,
     pbr_functions::alpha_discard,
+    mesh_view_bindings::view,
 }
 
 #ifdef PREPASS_PIPELINE
@@ -8,32 +9,197 @@
     prepass_io::{VertexOutput, FragmentOutput},
     pbr_deferred_functions::deferred_output,
 }
+#import bevy_render::globals::Globals
+
+// the prepass view layout keeps globals right after the view instead of at mesh_view_bindings' slot
+@group(0) @binding(1) var<uniform> globals: Globals;
 #else
 #import bevy_pbr::{
     forward_io::{VertexOutput, FragmentOutput},
     pbr_functions::{apply_pbr_lighting, main_pass_post_lighting_processing},
+    mesh_view_bindings::globals,
 }
 #endif
 
+#import grid::sheet::{
+    grid_cells, hole_distance, holes, paper, paper_hash, paper_noise, paper_wrinkles, bump_normal,
+    triplanar_cells, triplanar_weights, triplanar_paper_cells,
+}
+
+const PI: f32 = 3.141592653589793;
+const SQRT_3: f32 = 1.7320508075688772;
+
+// cell fill patterns, matching CellPattern in cell_fill.rs
+const CELL_PATTERN_HATCHED: u32 = 1u;
+const CELL_PATTERN_CROSS_HATCHED: u32 = 2u;
+const CELL_PATTERN_DOTTED: u32 = 3u;
+
+// highlight styles, matching HighlightKind in cell_highlight.rs
+const HIGHLIGHT_FILL: u32 = 1u;
+const HIGHLIGHT_PULSE: u32 = 2u;
+
+// fog of war states, matching FogState in fog_of_war.rs
+const FOG_UNEXPLORED: u32 = 0u;
+const FOG_EXPLORED: u32 = 1u;
+
+// must match MAX_GRID_HIGHLIGHTS in grid/uniforms/shader_types.rs
+const MAX_GRID_HIGHLIGHTS: u32 = 64u;
+
+struct GridLines {
+    color: vec4<f32>,
+    line_widths: vec2<f32>,
+    interval: vec2<u32>,
+    offset: vec2<f32>,
+}
+
+struct GridPolar {
+    origin: vec2<f32>,
+    spokes: u32,
+}
+
+struct GridFade {
+    min_spacing_pixels: f32,
+    spacing_fade_pixels: f32,
+    distance_start: f32,
+    distance_end: f32,
+}
+
+struct GridRipple {
+    origin: vec3<f32>,
+    start_time: f32,
+    color: vec4<f32>,
+    speed: f32,
+    width: f32,
+    duration: f32,
+}
+
+struct GridReveal {
+    origin: vec3<f32>,
+    start_time: f32,
+    direction: vec3<f32>,
+    speed: f32,
+    softness: f32,
+}
+
+struct GridHeight {
+    origin: vec3<f32>,
+    up: vec3<f32>,
+    low_color: vec4<f32>,
+    high_color: vec4<f32>,
+    low: f32,
+    high: f32,
+    contour_color: vec4<f32>,
+    contour_interval: f32,
+    contour_width: f32,
+}
+
+struct GridFog {
+    origin: vec2<i32>,
+    outside: u32,
+    explored_fade: f32,
+}
+
+struct GridHighlightRect {
+    min: vec2<i32>,
+    max: vec2<i32>,
+    color: vec4<f32>,
+    style: u32,
+    outline_width: f32,
+    group: u32,
+}
+
+struct GridHighlights {
+    count: u32,
+    rects: array<GridHighlightRect, MAX_GRID_HIGHLIGHTS>,
+}
+
 @group(1) @binding(100) var<uniform> grid_color: vec4<f32>;
-@group(1) @binding(101) var<uniform> grid_subdivisions: vec2<u32>;
 @group(1) @binding(102) var<uniform> grid_line_widths: vec2<f32>;
+@group(1) @binding(103) var<uniform> major_lines: GridLines;
+@group(1) @binding(104) var<uniform> axis_lines: GridLines;
+@group(1) @binding(105) var<uniform> grid_fade: GridFade;
+@group(1) @binding(106) var<uniform> polar: GridPolar;
+@group(1) @binding(107) var cell_data: texture_2d<u32>;
+@group(1) @binding(108) var<uniform> cell_data_origin: vec2<i32>;
+@group(1) @binding(109) var<uniform> highlights: GridHighlights;
+@group(1) @binding(111) var<uniform> grid_scroll_offset: vec2<f32>;
+@group(1) @binding(112) var<uniform> ripple: GridRipple;
+@group(1) @binding(113) var<uniform> reveal: GridReveal;
+@group(1) @binding(115) var<uniform> margin_lines: GridLines;
+@group(1) @binding(117) var<uniform> y_axis_color: vec4<f32>;
+@group(1) @binding(118) var<uniform> height: GridHeight;
+@group(1) @binding(119) var fog_data: texture_2d<u32>;
+@group(1) @binding(120) var<uniform> fog: GridFog;
 
-fn sample_grid(
-    uv: vec2<f32>
+// fades between 0.0 and 1.0 as lines spaced uv_deriv apart per pixel cross the
+// configured minimum spacing
+fn spacing_fade(
+    uv_deriv: vec2<f32>
+) -> vec2<f32> {
+    if grid_fade.spacing_fade_pixels <= 0.0 {
+        return vec2<f32>(1.0, 1.0);
+    }
+
+    let spacing_pixels = 1.0 / max(uv_deriv, vec2<f32>(1e-6, 1e-6));
+    return saturate((spacing_pixels - grid_fade.min_spacing_pixels) / grid_fade.spacing_fade_pixels);
+}
+
+// fades from 1.0 to 0.0 as the fragment moves away from the camera
+fn distance_fade(
+    world_position: vec3<f32>
 ) -> f32 {
-    // Allows for further subdividing between UV coordinates
-    let grid_subdivisions_f32: vec2<f32> = vec2<f32>(f32(grid_subdivisions.x) + 1.0, f32(grid_subdivisions.y) + 1.0);
-    var multi_uv = uv * grid_subdivisions_f32;
+    if grid_fade.distance_end <= grid_fade.distance_start {
+        return 1.0;
+    }
 
-    // Make sure line width is between 0.0 and 1.0
-    let line_widths = saturate(grid_line_widths);
+    let view_distance = distance(view.world_position, world_position);
+    return 1.0 - smoothstep(grid_fade.distance_start, grid_fade.distance_end, view_distance);
+}
 
-    // difference of UV values between adjacent screen fragments
-    let uv_ddxy = vec4<f32>(dpdx(multi_uv), dpdy(multi_uv));
+// converts line widths given in grid cells into the units of a line_uv whose lines are
+// spacing cells apart, widths given in pixels are left for resolve_widths
+fn scale_widths(
+    widths: vec2<f32>,
+    spacing: vec2<f32>,
+) -> vec2<f32> {
+#ifdef GRID_LINE_WIDTH_PIXELS
+    return widths;
+#else
+    return widths / spacing;
+#endif
+}
 
-    // some distance calculation eventually used in antialiasing
-    let uv_deriv = vec2<f32>(length(uv_ddxy.xz), length(uv_ddxy.yw));
+// line widths in the units of a line_uv that changes by uv_deriv per pixel
+fn resolve_widths(
+    widths: vec2<f32>,
+    uv_deriv: vec2<f32>,
+) -> vec2<f32> {
+#ifdef GRID_LINE_WIDTH_PIXELS
+    // once lines are closer together than their width they would invert into background
+    return min(widths * uv_deriv, vec2<f32>(0.5, 0.5));
+#else
+    return widths;
+#endif
+}
+
+// difference of line_uv values between adjacent screen fragments along each axis
+fn line_deriv(
+    line_uv: vec2<f32>
+) -> vec2<f32> {
+    let uv_ddxy = vec4<f32>(dpdx(line_uv), dpdy(line_uv));
+    return vec2<f32>(length(uv_ddxy.xz), length(uv_ddxy.yw));
+}
+
+// coverage of the lines along each axis of line_uv, which has a line at every whole number
+// unless repeat is false in which case there is only a single line at 0.0
+fn grid_lines(
+    line_uv: vec2<f32>,
+    uv_deriv: vec2<f32>,
+    widths: vec2<f32>,
+    repeat: vec2<bool>,
+) -> vec2<f32> {
+    // Make sure line width is between 0.0 and 1.0
+    let line_widths = saturate(resolve_widths(widths, uv_deriv));
 
     // if the line_width is more than half the space provided for drawing it,
     // it's really the background then isn't it?
@@ -50,17 +216,568 @@ fn sample_grid(
     let line_aa = uv_deriv * 1.5;
 
     // these steps are magical
-    var grid_uv = abs(fract(multi_uv) * 2.0 - 1.0);
+    // a single line behaves like a repeating one with nothing past the first cell
+    let single_uv = 1.0 - min(abs(line_uv) * 2.0, vec2<f32>(1.0, 1.0));
+    var grid_uv = select(single_uv, abs(fract(line_uv) * 2.0 - 1.0), repeat);
     grid_uv = select(1.0 - grid_uv, grid_uv, invert_line);
     var grid2 = smoothstep(draw_width + line_aa, draw_width - line_aa, grid_uv);
     grid2 *= saturate(target_width / draw_width);
-    grid2 = mix(grid2, target_width, saturate(uv_deriv * 2.0 - 1.0));
-    grid2 = select(grid2, 1.0 - grid2, invert_line);
+    // repeating lines blur into their average coverage once they get denser than a pixel,
+    // a single line has no such average so it is left alone
+    grid2 = select(grid2, mix(grid2, target_width, saturate(uv_deriv * 2.0 - 1.0)), repeat);
+
+    // fade repeating lines out before they get close enough together to alias,
+    // each axis fades on its own so lines squashed at grazing angles go first
+    grid2 *= select(vec2<f32>(1.0, 1.0), spacing_fade(uv_deriv), repeat);
+    return select(grid2, 1.0 - grid2, invert_line);
+}
+
+fn sample_grid(
+    line_uv: vec2<f32>,
+    widths: vec2<f32>,
+    repeat: vec2<bool>,
+) -> f32 {
+    let grid2 = grid_lines(line_uv, line_deriv(line_uv), widths, repeat);
 
     // mix the x and y value to draw it if either x or y needs drawing
     return mix(grid2.x, 1.0, grid2.y);
 }
 
+// round dots of diameter widths.x centered on every whole number of p
+fn sample_dots(
+    p: vec2<f32>,
+    widths: vec2<f32>,
+) -> f32 {
+    let uv_deriv = line_deriv(p);
+    let deriv = max(uv_deriv.x, uv_deriv.y);
+
+    // like lines, dots are drawn at least a pixel wide and dimmed to make up for it
+    let target_radius = saturate(resolve_widths(widths, vec2<f32>(deriv, deriv)).x) * 0.5;
+    let draw_radius = clamp(target_radius, deriv * 0.5, 0.5);
+    let dot_aa = deriv * 0.75;
+
+    let corner_distance = length(p - round(p));
+    var dots = smoothstep(draw_radius + dot_aa, draw_radius - dot_aa, corner_distance);
+    dots *= saturate(target_radius / draw_radius);
+    dots = mix(dots, PI * target_radius * target_radius, saturate(deriv * 2.0 - 1.0));
+
+    let fade = spacing_fade(uv_deriv);
+    return dots * min(fade.x, fade.y);
+}
+
+// three families of lines one cell apart meeting at 60 degrees
+fn sample_isometric(
+    p: vec2<f32>,
+    widths: vec2<f32>,
+) -> f32 {
+    // distance from one line to the next in each family
+    let spacing = SQRT_3 * 0.5;
+
+    // project onto the normal of each family so every family has a line at each whole number
+    let flat_uv = vec2<f32>(p.y, dot(p, vec2<f32>(spacing, 0.5))) / spacing;
+    let steep_uv = dot(p, vec2<f32>(-spacing, 0.5)) / spacing;
+    let steep_pair = vec2<f32>(steep_uv, steep_uv);
+
+    let line_widths = scale_widths(vec2<f32>(widths.x, widths.x), vec2<f32>(spacing, spacing));
+    let flat_lines = grid_lines(flat_uv, line_deriv(flat_uv), line_widths, vec2<bool>(true, true));
+    let steep_lines = grid_lines(steep_pair, line_deriv(steep_pair), line_widths, vec2<bool>(true, true));
+
+    return 1.0 - (1.0 - flat_lines.x) * (1.0 - flat_lines.y) * (1.0 - steep_lines.x);
+}
+
+// pointy topped hexagons whose flat sides are one cell apart
+fn sample_hex(
+    p: vec2<f32>,
+    widths: vec2<f32>,
+) -> f32 {
+    let uv_deriv = line_deriv(p);
+    let deriv = max(uv_deriv.x, uv_deriv.y);
+
+    // hex centers form two interleaved rectangular lattices, pick the closer center
+    let lattice = vec2<f32>(1.0, SQRT_3);
+    let half_lattice = lattice * 0.5;
+    let a = p - lattice * floor(p / lattice) - half_lattice;
+    let shifted = p - half_lattice;
+    let b = shifted - lattice * floor(shifted / lattice) - half_lattice;
+    let local = abs(select(b, a, dot(a, a) < dot(b, b)));
+
+    // distance to the nearest edge, 0.0 on the edge and 0.5 at the center
+    let edge_distance = 0.5 - max(local.x, dot(local, vec2<f32>(0.5, SQRT_3 * 0.5)));
+
+    let target_width = saturate(resolve_widths(widths, vec2<f32>(deriv, deriv)).x) * 0.5;
+    let draw_width = clamp(target_width, deriv * 0.5, 0.5);
+    let line_aa = deriv * 0.75;
+
+    var hex = smoothstep(draw_width + line_aa, draw_width - line_aa, edge_distance);
+    hex *= saturate(target_width / draw_width);
+    // hex edges cover about twice their width of the sheet
+    hex = mix(hex, saturate(target_width * 4.0), saturate(deriv * 2.0 - 1.0));
+
+    let fade = spacing_fade(uv_deriv);
+    return hex * min(fade.x, fade.y);
+}
+
+// rings every interval.x cells around the polar origin crossed by a spoke every
+// interval.y spokes
+fn sample_polar(
+    cells: vec2<f32>,
+    widths: vec2<f32>,
+    interval: vec2<f32>,
+) -> f32 {
+    let d = cells - polar.origin;
+    let radius = max(length(d), 1e-4);
+    let spokes = f32(polar.spokes) / interval.y;
+
+    // atan2 jumps by a whole number of spokes at its seam, so the lines stay continuous
+    let polar_uv = vec2<f32>(radius / interval.x, atan2(d.y, d.x) / (2.0 * PI) * spokes);
+
+    // the seam would also spike the derivatives, so the angular one is worked out by hand
+    let cells_deriv = line_deriv(cells);
+    let footprint = max(cells_deriv.x, cells_deriv.y);
+    let radians_per_spoke = 2.0 * PI / spokes;
+    let uv_deriv = vec2<f32>(footprint / interval.x, footprint / (radius * radians_per_spoke));
+
+    // spokes keep the same width all the way along
+    var line_widths = scale_widths(widths, vec2<f32>(interval.x, radius * radians_per_spoke));
+#ifndef GRID_LINE_WIDTH_PIXELS
+    // spokes crowd together near the origin, don't let them invert into background
+    line_widths.y = min(line_widths.y, 0.5);
+#endif
+    let grid2 = grid_lines(polar_uv, uv_deriv, line_widths, vec2<bool>(true, true));
+
+    return mix(grid2.x, 1.0, grid2.y);
+}
+
+// samples the material's pattern with lines every interval cells, starting at offset
+fn sample_pattern(
+    cells: vec2<f32>,
+    widths: vec2<f32>,
+    interval: vec2<f32>,
+    offset: vec2<f32>,
+) -> f32 {
+#ifdef GRID_PATTERN_POLAR
+    return sample_polar(cells, widths, interval);
+#else
+    let p = (cells - offset) / interval;
+    let pattern_widths = scale_widths(widths, interval);
+#ifdef GRID_PATTERN_DOTS
+    return sample_dots(p, pattern_widths);
+#else ifdef GRID_PATTERN_ISOMETRIC
+    return sample_isometric(p, pattern_widths);
+#else ifdef GRID_PATTERN_HEX
+    return sample_hex(p, pattern_widths);
+#else
+    return sample_grid(p, pattern_widths, vec2<bool>(true, true));
+#endif
+#endif
+}
+
+// samples a tier of lines spaced lines.interval cells apart
+fn sample_lines(
+    cells: vec2<f32>,
+    lines: GridLines,
+) -> f32 {
+    let repeat = lines.interval > vec2<u32>(0u, 0u);
+    if all(repeat) {
+        return sample_pattern(cells, lines.line_widths, vec2<f32>(lines.interval), lines.offset);
+    }
+
+    // single lines are always straight
+    let interval = select(vec2<f32>(1.0, 1.0), vec2<f32>(lines.interval), repeat);
+    return sample_grid((cells - lines.offset) / interval, scale_widths(lines.line_widths, interval), repeat);
+}
+
+// coverage of each of the two axis lines, x covers the line running along grid y
+// and y the line running along grid x
+fn sample_axes(
+    cells: vec2<f32>
+) -> vec2<f32> {
+    // axis lines are always straight, whatever the pattern
+    let repeat = axis_lines.interval > vec2<u32>(0u, 0u);
+    let interval = select(vec2<f32>(1.0, 1.0), vec2<f32>(axis_lines.interval), repeat);
+    let axis_uv = (cells - axis_lines.offset) / interval;
+    return grid_lines(axis_uv, line_deriv(axis_uv), scale_widths(axis_lines.line_widths, interval), repeat);
+}
+
+// height of a world position above height.origin, in units of height.up
+fn grid_height(
+    world_position: vec3<f32>
+) -> f32 {
+    let up_squared = max(dot(height.up, height.up), 1e-12);
+    return dot(world_position - height.origin, height.up) / up_squared;
+}
+
+// tint of the paper at height h, transparent when the tint is disabled
+fn height_tint(
+    h: f32
+) -> vec4<f32> {
+    if height.high == height.low {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let t = saturate((h - height.low) / (height.high - height.low));
+    return mix(height.low_color, height.high_color, t);
+}
+
+// coverage of the contour lines at height h, they are spaced like a tier of repeating lines
+// so they fade out the same way once they crowd together on steep slopes
+fn sample_contours(
+    h: f32
+) -> f32 {
+    if height.contour_interval <= 0.0 {
+        return 0.0;
+    }
+
+    let contour_uv = vec2<f32>(h / height.contour_interval);
+    let widths = scale_widths(vec2<f32>(height.contour_width), vec2<f32>(height.contour_interval));
+    return grid_lines(contour_uv, line_deriv(contour_uv), widths, vec2<bool>(true, true)).x;
+}
+
+// antialiased coverage of stripes of the given width centered on every whole number of u
+fn stripes(
+    u: f32,
+    width: f32,
+) -> f32 {
+    let deriv = length(vec2<f32>(dpdx(u), dpdy(u)));
+    let stripe = smoothstep(width * 0.5 + deriv, width * 0.5 - deriv, abs(fract(u + 0.5) - 0.5));
+    return mix(stripe, width, saturate(deriv * 2.0 - 1.0));
+}
+
+fn srgb_to_linear(
+    color: vec3<f32>
+) -> vec3<f32> {
+    return pow(color, vec3<f32>(2.2, 2.2, 2.2));
+}
+
+// color and opacity painted into the cell under cells by CellFills
+fn sample_cell_fill(
+    cells: vec2<f32>
+) -> vec4<f32> {
+    // cell coordinates count from the axis lines, the same as GridCoord
+    let grid = cells - axis_lines.offset;
+
+    // pattern coverage needs derivatives, so it has to be worked out before branching on the cell
+    let hatch = stripes((grid.x + grid.y) * 2.0, 0.35);
+    let cross_hatch = max(hatch, stripes((grid.x - grid.y) * 2.0, 0.35));
+    let dot_uv = grid * 3.0;
+    let dot_deriv = max(length(dpdx(dot_uv)), length(dpdy(dot_uv)));
+    let dot_distance = length(fract(dot_uv) - 0.5);
+    let dots = mix(smoothstep(0.25 + dot_deriv, 0.25 - dot_deriv, dot_distance), PI * 0.0625, saturate(dot_deriv * 2.0 - 1.0));
+
+    let texel = vec2<i32>(floor(grid)) - cell_data_origin;
+    let size = vec2<i32>(textureDimensions(cell_data));
+    if any(texel < vec2<i32>(0, 0)) || any(texel >= size) {
+        return vec4<f32>(0.0, 0.0, 0.0, 0.0);
+    }
+
+    let data = textureLoad(cell_data, texel, 0);
+    let color = unpack4x8unorm(data.r);
+
+    var coverage = 1.0;
+    if data.g == CELL_PATTERN_HATCHED {
+        coverage = hatch;
+    } else if data.g == CELL_PATTERN_CROSS_HATCHED {
+        coverage = cross_hatch;
+    } else if data.g == CELL_PATTERN_DOTTED {
+        coverage = dots;
+    }
+
+    return vec4<f32>(srgb_to_linear(color.rgb), color.a * coverage);
+}
+
+// how far a cell has faded into blank paper under the fog of war, from 0.0 to 1.0
+fn cell_fog(
+    cell: vec2<i32>
+) -> f32 {
+    let texel = cell - fog.origin;
+    let size = vec2<i32>(textureDimensions(fog_data));
+    var state = fog.outside;
+    if all(texel >= vec2<i32>(0, 0)) && all(texel < size) {
+        state = textureLoad(fog_data, texel, 0).r;
+    }
+
+    if state == FOG_UNEXPLORED {
+        return 1.0;
+    } else if state == FOG_EXPLORED {
+        return fog.explored_fade;
+    }
+    return 0.0;
+}
+
+// fog of war at cells, blended between the centers of neighbouring cells
+fn sample_fog(
+    cells: vec2<f32>
+) -> f32 {
+    let grid = cells - axis_lines.offset - vec2<f32>(0.5, 0.5);
+    let first = vec2<i32>(floor(grid));
+    let t = fract(grid);
+    let top = mix(cell_fog(first), cell_fog(first + vec2<i32>(1, 0)), t.x);
+    let bottom = mix(cell_fog(first + vec2<i32>(0, 1)), cell_fog(first + vec2<i32>(1, 1)), t.x);
+    return mix(top, bottom, t.y);
+}
+
+// how much of the lines the reveal front has drawn on so far, from 0.0 to 1.0
+fn reveal_progress(
+    world_position: vec3<f32>
+) -> f32 {
+    if reveal.speed <= 0.0 {
+        return 1.0;
+    }
+
+    let front = (globals.time - reveal.start_time) * reveal.speed;
+    let offset = world_position - reveal.origin;
+    let travelled = select(length(offset), dot(offset, reveal.direction), any(reveal.direction != vec3<f32>(0.0)));
+    return 1.0 - smoothstep(front - reveal.softness, front, travelled);
+}
+
+// strength of the ripple ring at a world position, from 0.0 to 1.0
+fn ripple_strength(
+    world_position: vec3<f32>
+) -> f32 {
+    let elapsed = globals.time - ripple.start_time;
+    if ripple.duration <= 0.0 || elapsed < 0.0 || elapsed > ripple.duration {
+        return 0.0;
+    }
+
+    let ring_distance = abs(distance(world_position, ripple.origin) - elapsed * ripple.speed);
+    let ring = 1.0 - smoothstep(0.0, max(ripple.width * 0.5, 1e-4), ring_distance);
+    return ring * (1.0 - elapsed / ripple.duration);
+}
+
+// whether a cell lies in any highlighted rect of a group, group 0 stands alone
+fn in_highlight_group(
+    cell: vec2<i32>,
+    group: u32,
+) -> bool {
+    if group == 0u {
+        return false;
+    }
+    for (var i = 0u; i < min(highlights.count, MAX_GRID_HIGHLIGHTS); i++) {
+        let rect = highlights.rects[i];
+        if rect.group == group && all(cell >= rect.min) && all(cell <= rect.max) {
+            return true;
+        }
+    }
+    return false;
+}
+
+// distance in cells to the nearest edge of rect that borders cells outside its group, edges
+// shared with the group's other rects are left out but the corners where they meet are kept
+fn group_outline_edge(
+    rect: GridHighlightRect,
+    grid: vec2<f32>,
+) -> f32 {
+    let to_min = grid - vec2<f32>(rect.min);
+    let to_max = vec2<f32>(rect.max + vec2<i32>(1, 1)) - grid;
+    let cell = vec2<i32>(floor(grid));
+
+    let left = !in_highlight_group(vec2<i32>(rect.min.x - 1, cell.y), rect.group);
+    let right = !in_highlight_group(vec2<i32>(rect.max.x + 1, cell.y), rect.group);
+    let bottom = !in_highlight_group(vec2<i32>(cell.x, rect.min.y - 1), rect.group);
+    let top = !in_highlight_group(vec2<i32>(cell.x, rect.max.y + 1), rect.group);
+
+    var edge = 1e6;
+    if left {
+        edge = min(edge, to_min.x);
+    }
+    if right {
+        edge = min(edge, to_max.x);
+    }
+    if bottom {
+        edge = min(edge, to_min.y);
+    }
+    if top {
+        edge = min(edge, to_max.y);
+    }
+
+    // an inner corner of the shape, where the diagonal cell is outside the group
+    let outer_min = rect.min - vec2<i32>(1, 1);
+    let outer_max = rect.max + vec2<i32>(1, 1);
+    if !left && !bottom && !in_highlight_group(outer_min, rect.group) {
+        edge = min(edge, max(to_min.x, to_min.y));
+    }
+    if !right && !bottom && !in_highlight_group(vec2<i32>(outer_max.x, outer_min.y), rect.group) {
+        edge = min(edge, max(to_max.x, to_min.y));
+    }
+    if !left && !top && !in_highlight_group(vec2<i32>(outer_min.x, outer_max.y), rect.group) {
+        edge = min(edge, max(to_min.x, to_max.y));
+    }
+    if !right && !top && !in_highlight_group(outer_max, rect.group) {
+        edge = min(edge, max(to_max.x, to_max.y));
+    }
+    return edge;
+}
+
+// paints the highlighted rectangles over color
+fn apply_highlights(
+    cells: vec2<f32>,
+    color: vec4<f32>,
+) -> vec4<f32> {
+    // cell coordinates count from the axis lines, the same as GridCoord
+    let grid = cells - axis_lines.offset;
+    let grid_deriv = line_deriv(grid);
+
+    // fills breathe between a faint and a full tint about once a second
+    let pulse = 0.6 + 0.4 * sin(globals.time * 2.0 * PI);
+
+    var out = color;
+    for (var i = 0u; i < min(highlights.count, MAX_GRID_HIGHLIGHTS); i++) {
+        let rect = highlights.rects[i];
+        let rect_min = vec2<f32>(rect.min);
+        let rect_max = vec2<f32>(rect.max + vec2<i32>(1, 1));
+
+        // distance to the nearest edge in cells, negative outside the rect
+        let edge2 = min(grid - rect_min, rect_max - grid);
+        let edge = min(edge2.x, edge2.y);
+        let edge_aa = max(grid_deriv.x, grid_deriv.y);
+        let inside = saturate(edge / edge_aa + 0.5);
+
+        // outlines sit just inside the rect so neighbouring cells don't overlap
+        let outline_width = max(rect.outline_width, edge_aa);
+        var outline_edge = edge;
+        if rect.group != 0u && edge < outline_width {
+            outline_edge = group_outline_edge(rect, grid);
+        }
+        let outline = inside * (1.0 - smoothstep(outline_width - edge_aa, outline_width, outline_edge));
+
+        var coverage = outline;
+        if rect.style == HIGHLIGHT_FILL {
+            coverage = inside;
+        } else if rect.style == HIGHLIGHT_PULSE {
+            coverage = max(outline, inside * 0.35 * pulse);
+        }
+
+        out = mix(out, vec4<f32>(rect.color.rgb, out.a), coverage * rect.color.a);
+    }
+
+    return out;
+}
+
+// brightness variation of the paper fibers, between -1.0 and 1.0
+fn paper_fibers(
+    cells: vec2<f32>
+) -> f32 {
+    let p = cells / paper.fiber_scale;
+
+    // short fibers lie in two rough directions, with a fine grain in between
+    let along = paper_noise(p * vec2<f32>(0.25, 2.0));
+    let across = paper_noise(vec2<f32>(p.x + p.y, p.x - p.y) * vec2<f32>(2.0, 0.25) + 37.0);
+    let grain = paper_noise(p * 4.0 + 113.0);
+    let fibers = along + across - 1.0 + (grain - 0.5) * 0.5;
+
+    // once fibers shrink below a pixel they only add noise
+    let p_deriv = line_deriv(p);
+    return fibers * (1.0 - smoothstep(0.25, 1.0, max(p_deriv.x, p_deriv.y)));
+}
+
+// how strongly coffee rings have stained the paper, from 0.0 to 1.0
+fn coffee_stains(
+    cells: vec2<f32>
+) -> f32 {
+    let p = cells / paper.stain_scale;
+    let site = vec2<i32>(floor(p));
+    let wobble = (paper_noise(p * 6.0 + 211.0) - 0.5) * 0.08;
+
+    // a site may hold a single stain that can spill into the sites around it
+    var stain = 0.0;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let id = site + vec2<i32>(x, y);
+            if paper_hash(id * 3 + vec2<i32>(17, 0)) > paper.stain_density {
+                continue;
+            }
+
+            let center = vec2<f32>(id) + vec2<f32>(paper_hash(id * 3 + vec2<i32>(0, 17)), paper_hash(id * 3 + vec2<i32>(17, 17)));
+            let radius = mix(0.2, 0.45, paper_hash(id * 3 + vec2<i32>(29, 5)));
+            let d = length(p - center) / radius + wobble;
+
+            // coffee dries darkest along the rim of the ring
+            let rim = smoothstep(0.82, 0.97, d) * (1.0 - smoothstep(0.97, 1.03, d));
+            let puddle = (1.0 - smoothstep(0.9, 1.0, d)) * 0.3;
+            stain = max(stain, max(rim, puddle));
+        }
+    }
+
+    return stain;
+}
+
+// how far the paper has yellowed towards the edges of the sheet, from 0.0 to 1.0
+fn edge_yellowing(
+    cells: vec2<f32>
+) -> f32 {
+    if any(paper.sheet_size <= vec2<f32>(0.0, 0.0)) || paper.edge_width <= 0.0 {
+        return 0.0;
+    }
+
+    let edges = min(cells, paper.sheet_size - cells);
+    let ragged = (paper_noise(cells * 0.3 + 151.0) - 0.5) * paper.edge_width * 0.5;
+    return 1.0 - smoothstep(0.0, paper.edge_width, min(edges.x, edges.y) + ragged);
+}
+
+// how much of the paper at cells has been punched out by the binder holes, from 0.0 to 1.0
+fn punched_holes(
+    cells: vec2<f32>
+) -> f32 {
+    let cells_deriv = line_deriv(cells);
+    if holes.count == 0u {
+        return 0.0;
+    }
+
+    let hole_aa = max(cells_deriv.x, cells_deriv.y);
+    return 1.0 - smoothstep(holes.radius - hole_aa, holes.radius + hole_aa, hole_distance(cells));
+}
+
+// paints the fills, lines and highlights of the grid at cells onto base_color
+fn paint_grid(
+    cells: vec2<f32>,
+    base_color: vec4<f32>,
+    world_position: vec3<f32>,
+) -> vec4<f32> {
+    var color = base_color;
+
+    // tint the paper by its height, fills and lines are painted over the tint
+    let h = grid_height(world_position);
+    let tint = height_tint(h);
+    color = vec4<f32>(mix(color.rgb, tint.rgb, tint.a), color.a);
+
+    // paint the cell fills onto the paper underneath the lines
+    let cell_fill = sample_cell_fill(cells);
+    color = vec4<f32>(mix(color.rgb, cell_fill.rgb, cell_fill.a), color.a);
+
+    // mix each tier of lines into base color, later tiers draw over earlier ones
+    let fade = distance_fade(world_position) * reveal_progress(world_position);
+    let scrolled_cells = cells - grid_scroll_offset;
+    let grid_mix = sample_pattern(scrolled_cells, grid_line_widths, vec2<f32>(1.0, 1.0), vec2<f32>(0.0, 0.0)) * fade;
+    color = mix(color, grid_color, grid_mix * grid_color[3]);
+
+    let major_mix = sample_lines(scrolled_cells, major_lines) * fade;
+    color = mix(color, major_lines.color, major_mix * major_lines.color[3]);
+
+    let contour_mix = sample_contours(h) * fade;
+    color = mix(color, height.contour_color, contour_mix * height.contour_color.a);
+
+    let margin_mix = sample_lines(cells, margin_lines) * fade;
+    color = mix(color, margin_lines.color, margin_mix * margin_lines.color[3]);
+
+    // each axis gets its own color, the x axis is drawn over the y axis where they cross
+    let axes_mix = sample_axes(cells) * fade;
+    let y_axis = select(axis_lines.color, y_axis_color, y_axis_color.a > 0.0);
+    color = mix(color, y_axis, axes_mix.x * y_axis.a);
+    color = mix(color, axis_lines.color, axes_mix.y * axis_lines.color[3]);
+    let axis_mix = max(axes_mix.x, axes_mix.y);
+
+    // the ripple lights up the lines it passes and tints the paper between them a little
+    let line_mix = max(max(max(grid_mix, major_mix), max(margin_mix, axis_mix)), contour_mix);
+    let ripple_mix = ripple_strength(world_position) * mix(0.2, 1.0, line_mix);
+    color = mix(color, vec4<f32>(ripple.color.rgb, color.a), ripple_mix * ripple.color.a);
+
+    // cells that haven't been seen lately fade back into blank paper
+    color = mix(color, base_color, sample_fog(cells));
+
+    // hovered, selected and other highlighted cells go over everything else
+    return apply_highlights(cells, color);
+}
+
 @fragment
 fn fragment(
     in: VertexOutput,
@@ -69,12 +786,55 @@ fn fragment(
     // generate a PbrInput struct from the StandardMaterial bindings
     var pbr_input = pbr_input_from_standard_material(in, is_front);
 
-    // alpha discard
-    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+#ifdef GRID_TRIPLANAR
+    // project the grid along each world axis, the top is laid out like a GridBox
+    let world_cells = triplanar_cells(in.world_position.xyz);
+    let weights = triplanar_weights(in.world_normal);
+    let paper_cells = triplanar_paper_cells(world_cells, weights);
+#else
+    let paper_cells = grid_cells(in.uv);
+#endif
+
+    // each layer of the paper surface is optional, the checks only read uniforms so
+    // derivatives stay valid inside them
+    var fibers = 0.0;
+    if paper.fiber_strength != 0.0 {
+        fibers = paper_fibers(paper_cells);
+    }
+    if paper.wrinkle_height != 0.0 {
+        pbr_input.N = bump_normal(pbr_input.N, in.world_position.xyz, paper_wrinkles(paper_cells));
+    }
+    var stains = 0.0;
+    if paper.stain_density > 0.0 {
+        stains = coffee_stains(paper_cells);
+    }
+    let yellowing = edge_yellowing(paper_cells);
+#ifdef GRID_TRIPLANAR
+    // binder holes only make sense on a sheet laid out by its uvs
+    let punched = 0.0;
+#else
+    let punched = punched_holes(paper_cells);
+#endif
+
+    // fibers are part of the paper so the grid is printed over them
+    var base_color = pbr_input.material.base_color;
+    base_color = vec4<f32>(base_color.rgb * (1.0 + fibers * paper.fiber_strength), base_color.a);
 
-    // mix the grid color into base color
-    let grid_mix = sample_grid(in.uv);
-    pbr_input.material.base_color = mix(pbr_input.material.base_color, grid_color, grid_mix * grid_color[3]);
+#ifdef GRID_TRIPLANAR
+    base_color = paint_grid(world_cells.zy, base_color, in.world_position.xyz) * weights.x
+        + paint_grid(world_cells.xz, base_color, in.world_position.xyz) * weights.y
+        + paint_grid(world_cells.xy, base_color, in.world_position.xyz) * weights.z;
+#else
+    base_color = paint_grid(paper_cells, base_color, in.world_position.xyz);
+#endif
+
+    // stains and age soak into the paper and the ink alike
+    base_color = vec4<f32>(mix(base_color.rgb, base_color.rgb * paper.stain_color.rgb, stains * paper.stain_color.a), base_color.a);
+    base_color = vec4<f32>(mix(base_color.rgb, base_color.rgb * paper.edge_color.rgb, yellowing * paper.edge_color.a), base_color.a);
+    pbr_input.material.base_color = vec4<f32>(base_color.rgb, base_color.a * (1.0 - punched));
+
+    // alpha discard, after painting so the holes can be cut out
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
 
 #ifdef PREPASS_PIPELINE
     // in deferred mode we can't modify anything after that, as lighting is run in a separate fullscreen shader.
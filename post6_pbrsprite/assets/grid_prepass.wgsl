@@ -0,0 +1,98 @@
+#import bevy_pbr::{
+    prepass_io::VertexOutput,
+    pbr_prepass_functions::prepass_alpha_discard,
+}
+
+#ifdef PREPASS_FRAGMENT
+#import bevy_pbr::{
+    prepass_io::FragmentOutput,
+    pbr_prepass_functions::calculate_motion_vector,
+    pbr_functions::prepare_world_normal,
+    pbr_bindings::material,
+    pbr_types::STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT,
+}
+#endif
+
+#import grid::sheet::{
+    grid_cells, hole_distance, holes, paper, paper_wrinkles, bump_normal, triplanar_cells,
+    triplanar_weights, triplanar_paper_cells,
+}
+
+// Depth and normal prepass for the grid material. The lines only change the color of the
+// paper, so all that's left to do here is cutting out the binder holes and tilting the
+// normals along the wrinkles.
+
+// discards fragments inside the binder holes, the prepass has no use for antialiased edges.
+// like the main pass, holes are only cut when the material's alpha mode may discard
+fn cut_holes(
+    in: VertexOutput
+) {
+#ifdef MAY_DISCARD
+#ifdef VERTEX_UVS
+#ifndef GRID_TRIPLANAR
+    if holes.count == 0u {
+        return;
+    }
+
+    if hole_distance(grid_cells(in.uv)) < holes.radius {
+        discard;
+    }
+#endif
+#endif
+#endif
+}
+
+#ifdef NORMAL_PREPASS
+// cells of the paper under a fragment, laid out the same way as in the main pass
+fn paper_cells(
+    in: VertexOutput
+) -> vec2<f32> {
+#ifdef GRID_TRIPLANAR
+    let world_cells = triplanar_cells(in.world_position.xyz);
+    return triplanar_paper_cells(world_cells, triplanar_weights(in.world_normal));
+#else
+    return grid_cells(in.uv);
+#endif
+}
+#endif
+
+#ifdef PREPASS_FRAGMENT
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    cut_holes(in);
+    prepass_alpha_discard(in);
+
+    var out: FragmentOutput;
+
+#ifdef DEPTH_CLAMP_ORTHO
+    out.frag_depth = in.clip_position_unclamped.z;
+#endif
+
+#ifdef NORMAL_PREPASS
+    let double_sided = (material.flags & STANDARD_MATERIAL_FLAGS_DOUBLE_SIDED_BIT) != 0u;
+    var normal = prepare_world_normal(in.world_normal, double_sided, is_front);
+    // screen space effects have to see the same creases the main pass shades
+    if paper.wrinkle_height != 0.0 {
+        normal = bump_normal(normal, in.world_position.xyz, paper_wrinkles(paper_cells(in)));
+    }
+    out.normal = vec4<f32>(normal * 0.5 + vec3<f32>(0.5), 1.0);
+#endif
+
+#ifdef MOTION_VECTOR_PREPASS
+    out.motion_vector = calculate_motion_vector(in.world_position, in.previous_world_position);
+#endif
+
+    return out;
+}
+#else
+@fragment
+fn fragment(
+    in: VertexOutput,
+) {
+    cut_holes(in);
+    prepass_alpha_discard(in);
+}
+#endif
@@ -0,0 +1,141 @@
+#define_import_path grid::sheet
+
+// The sheet of paper under the grid, shared by grid_material.wgsl and grid_prepass.wgsl so
+// both passes agree on where the cells, the binder holes and the wrinkles are.
+
+struct GridTriplanar {
+    origin: vec3<f32>,
+    cell_size: f32,
+    sharpness: f32,
+}
+
+struct GridPaper {
+    seed: u32,
+    fiber_strength: f32,
+    fiber_scale: f32,
+    wrinkle_height: f32,
+    wrinkle_scale: f32,
+    stain_color: vec4<f32>,
+    stain_density: f32,
+    stain_scale: f32,
+    edge_color: vec4<f32>,
+    edge_width: f32,
+    sheet_size: vec2<f32>,
+}
+
+struct GridHoles {
+    first: vec2<f32>,
+    spacing: vec2<f32>,
+    count: u32,
+    radius: f32,
+}
+
+@group(1) @binding(101) var<uniform> grid_subdivisions: vec2<u32>;
+@group(1) @binding(110) var<uniform> triplanar: GridTriplanar;
+@group(1) @binding(114) var<uniform> paper: GridPaper;
+@group(1) @binding(116) var<uniform> holes: GridHoles;
+
+// converts mesh UVs into grid cell coordinates
+fn grid_cells(
+    uv: vec2<f32>
+) -> vec2<f32> {
+    // Allows for further subdividing between UV coordinates
+    let grid_subdivisions_f32: vec2<f32> = vec2<f32>(f32(grid_subdivisions.x) + 1.0, f32(grid_subdivisions.y) + 1.0);
+    return uv * grid_subdivisions_f32;
+}
+
+// distance in cells from cells to the center of the closest binder hole, only meaningful
+// when holes.count isn't 0
+fn hole_distance(
+    cells: vec2<f32>
+) -> f32 {
+    // the holes sit in a row, only the closest one matters
+    let step = max(dot(holes.spacing, holes.spacing), 1e-6);
+    let index = clamp(round(dot(cells - holes.first, holes.spacing) / step), 0.0, f32(holes.count - 1u));
+    return length(cells - holes.first - holes.spacing * index);
+}
+
+// grid cells of a world position projected along each world axis, the top is laid out like
+// a GridBox
+fn triplanar_cells(
+    world_position: vec3<f32>
+) -> vec3<f32> {
+    return (world_position - triplanar.origin) / triplanar.cell_size;
+}
+
+// how much each axis' projection contributes to a surface facing world_normal, adding up to 1
+fn triplanar_weights(
+    world_normal: vec3<f32>
+) -> vec3<f32> {
+    let weights = pow(abs(normalize(world_normal)), vec3<f32>(triplanar.sharpness));
+    return weights / (weights.x + weights.y + weights.z);
+}
+
+// the paper itself only follows the projection facing the surface the most
+fn triplanar_paper_cells(
+    world_cells: vec3<f32>,
+    weights: vec3<f32>,
+) -> vec2<f32> {
+    return select(select(world_cells.xy, world_cells.xz, weights.y > weights.z), world_cells.zy, weights.x > max(weights.y, weights.z));
+}
+
+// random value between 0.0 and 1.0 for an integer position, different for every paper seed
+fn paper_hash(
+    p: vec2<i32>
+) -> f32 {
+    var h = (bitcast<u32>(p.x) * 0x8da6b343u) ^ (bitcast<u32>(p.y) * 0xd8163841u) ^ (paper.seed * 0xcb1ab31fu);
+    h ^= h >> 16u;
+    h *= 0x7feb352du;
+    h ^= h >> 15u;
+    h *= 0x846ca68bu;
+    h ^= h >> 16u;
+    return f32(h) / 4294967295.0;
+}
+
+// smoothly interpolated paper_hash, features are about one unit of p across
+fn paper_noise(
+    p: vec2<f32>
+) -> f32 {
+    let i = vec2<i32>(floor(p));
+    let f = fract(p);
+    let u = f * f * (3.0 - 2.0 * f);
+
+    let bottom = mix(paper_hash(i), paper_hash(i + vec2<i32>(1, 0)), u.x);
+    let top = mix(paper_hash(i + vec2<i32>(0, 1)), paper_hash(i + vec2<i32>(1, 1)), u.x);
+    return mix(bottom, top, u.y);
+}
+
+// height of the wrinkles pressed into the paper, in world units
+fn paper_wrinkles(
+    cells: vec2<f32>
+) -> f32 {
+    var p = cells / paper.wrinkle_scale + 71.0;
+
+    // creases are the ridges of the noise, smaller ones ride on top of larger ones
+    var height = 0.0;
+    var amplitude = 1.0;
+    for (var octave = 0; octave < 3; octave++) {
+        let ridge = 1.0 - abs(paper_noise(p) * 2.0 - 1.0);
+        height += ridge * ridge * amplitude;
+        p = vec2<f32>(p.x * 1.6 - p.y * 1.2, p.x * 1.2 + p.y * 1.6);
+        amplitude *= 0.45;
+    }
+
+    return height * paper.wrinkle_height;
+}
+
+// tilts normal to follow the slope of height across the surface at world_position
+fn bump_normal(
+    normal: vec3<f32>,
+    world_position: vec3<f32>,
+    height: f32,
+) -> vec3<f32> {
+    let position_dx = dpdx(world_position);
+    let position_dy = dpdy(world_position);
+    let r1 = cross(position_dy, normal);
+    let r2 = cross(normal, position_dx);
+    let det = dot(position_dx, r1);
+
+    let surface_gradient = sign(det) * (dpdx(height) * r1 + dpdy(height) * r2);
+    return normalize(abs(det) * normal - surface_gradient);
+}
@@ -0,0 +1,91 @@
+#import bevy_pbr::{
+    mesh_functions,
+    mesh_view_bindings::view,
+    view_transformations::position_world_to_clip,
+    forward_io::{Vertex, VertexOutput, FragmentOutput},
+    pbr_fragment::pbr_input_from_standard_material,
+    pbr_functions::{alpha_discard, apply_pbr_lighting, main_pass_post_lighting_processing},
+    pbr_types::STANDARD_MATERIAL_FLAGS_UNLIT_BIT,
+}
+#import bevy_render::instance_index::get_instance_index
+
+// Ink lines are ribbons laid along a curve. Each vertex sits on the center of the curve,
+// its tangent points across the line and uv.y says which side it belongs on.
+
+@group(1) @binding(300) var<uniform> ink_width: f32;
+@group(1) @binding(301) var<uniform> ink_dash: vec2<f32>;
+
+@vertex
+fn vertex(
+    vertex: Vertex
+) -> VertexOutput {
+    var out: VertexOutput;
+
+    let model = mesh_functions::get_model_matrix(vertex.instance_index);
+    let center = mesh_functions::mesh_position_local_to_world(model, vec4<f32>(vertex.position, 1.0));
+    // the tangent is longer than 1.0 at sharp corners, so both sides of the join keep their width
+    let across = (model * vec4<f32>(vertex.tangent.xyz, 0.0)).xyz;
+
+    // widen the ribbon by a pixel on each side so the antialiased edge has room,
+    // no matter how thin the line is
+    let clip = position_world_to_clip(center.xyz);
+    let pixel = clip.w * 2.0 / (view.viewport.w * view.projection[1][1]);
+    let reach = ink_width * 0.5 + pixel;
+
+    out.world_position = vec4<f32>(center.xyz + across * reach * vertex.uv.y, 1.0);
+    out.position = position_world_to_clip(out.world_position.xyz);
+    out.world_normal = mesh_functions::mesh_normal_local_to_world(vertex.normal, get_instance_index(vertex.instance_index));
+    // uv.y becomes the distance from the center of the line
+    out.uv = vec2<f32>(vertex.uv.x, vertex.uv.y * reach);
+    out.world_tangent = vec4<f32>(normalize(across), 1.0);
+
+#ifdef VERTEX_OUTPUT_INSTANCE_INDEX
+    out.instance_index = get_instance_index(vertex.instance_index);
+#endif
+
+#ifdef BASE_INSTANCE_WORKAROUND
+    // same push constant workaround as bevy's mesh vertex shader
+    out.position.x += min(f32(get_instance_index(0u)), 0.0);
+#endif
+
+    return out;
+}
+
+@fragment
+fn fragment(
+    in: VertexOutput,
+    @builtin(front_facing) is_front: bool,
+) -> FragmentOutput {
+    var pbr_input = pbr_input_from_standard_material(in, is_front);
+
+    // like the grid lines, ink is drawn at least a pixel wide and dimmed to make up for it
+    let across = in.uv.y;
+    let deriv = max(fwidth(across), 1e-6);
+    let half_width = ink_width * 0.5;
+    let draw_width = max(half_width, deriv * 0.5);
+    var coverage = saturate((draw_width - abs(across)) / deriv + 0.5);
+    coverage *= half_width / draw_width;
+
+    // dashes are laid out along uv.x, the line starts with a whole dash
+    let along_deriv = max(fwidth(in.uv.x), 1e-6);
+    if ink_dash.x > 0.0 && ink_dash.y > 0.0 {
+        let period = ink_dash.x + ink_dash.y;
+        let along = in.uv.x - ink_dash.x * 0.5;
+        let from_center = abs(along - round(along / period) * period);
+        coverage *= saturate((ink_dash.x * 0.5 - from_center) / along_deriv + 0.5);
+    }
+
+    let base_color = pbr_input.material.base_color;
+    pbr_input.material.base_color = vec4<f32>(base_color.rgb, base_color.a * coverage);
+    pbr_input.material.base_color = alpha_discard(pbr_input.material, pbr_input.material.base_color);
+
+    var out: FragmentOutput;
+    if (pbr_input.material.flags & STANDARD_MATERIAL_FLAGS_UNLIT_BIT) == 0u {
+        out.color = apply_pbr_lighting(pbr_input);
+    } else {
+        out.color = pbr_input.material.base_color;
+    }
+    out.color = main_pass_post_lighting_processing(pbr_input, out.color);
+
+    return out;
+}
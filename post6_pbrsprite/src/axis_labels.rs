@@ -0,0 +1,212 @@
+use bevy::{prelude::*, ui::UiSystem};
+
+use crate::{grid::CameraTarget, grid_coord::GridLayout};
+
+pub struct AxisLabelsPlugin;
+
+impl Plugin for AxisLabelsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, AxisLabels::update.before(UiSystem::Layout));
+    }
+}
+
+/// Multiples of the tick interval tried in turn as the camera zooms out
+const THINNING_STEPS: [u32; 10] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1000];
+
+/// Numbers along the axes of the [`GridLayout`], drawn as text over the scene
+///
+/// Ticks sit every `interval` cells along both axes, counting cells from the origin. Once
+/// neighbouring labels get closer on screen than `min_spacing_pixels` only every second, fifth
+/// or tenth tick (and so on) keeps its label. Insert the resource to show the labels.
+#[derive(Resource, Debug, Clone)]
+pub struct AxisLabels {
+    pub font: Handle<Font>,
+    pub font_size: f32,
+
+    /// color of the labels along the grid's x axis
+    pub x_color: Color,
+
+    /// color of the labels along the grid's y axis
+    pub y_color: Color,
+
+    /// number of cells between two labeled ticks when zoomed in, usually the interval of the
+    /// major lines
+    pub interval: u32,
+
+    /// labels closer together than this on screen are thinned out
+    pub min_spacing_pixels: f32,
+
+    /// number of labels on either side of the camera target along each axis
+    pub reach: u32,
+
+    /// ui text entities showing the labels, reused from frame to frame
+    labels: Vec<Entity>,
+}
+
+impl AxisLabels {
+    pub fn new(font: Handle<Font>) -> Self {
+        Self {
+            font,
+            font_size: 16.0,
+            x_color: Color::BLACK,
+            y_color: Color::BLACK,
+            interval: 5,
+            min_spacing_pixels: 60.0,
+            reach: 16,
+            labels: Vec::new(),
+        }
+    }
+
+    pub fn with_colors(mut self, x_color: Color, y_color: Color) -> Self {
+        self.x_color = x_color;
+        self.y_color = y_color;
+        self
+    }
+
+    pub fn with_interval(mut self, interval: u32) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn with_min_spacing(mut self, min_spacing_pixels: f32) -> Self {
+        self.min_spacing_pixels = min_spacing_pixels;
+        self
+    }
+
+    /// number of cells between labeled ticks, the smallest multiple of the interval whose
+    /// labels are at least min_spacing_pixels apart around focus
+    fn label_step(&self, focus: Vec2, project: impl Fn(Vec2) -> Option<Vec2>) -> u32 {
+        let interval = self.interval.max(1);
+        let Some(center) = project(focus) else {
+            return interval;
+        };
+
+        for multiple in THINNING_STEPS {
+            let step = (interval * multiple) as f32;
+            let spacing = [Vec2::new(step, 0.0), Vec2::new(0.0, step)]
+                .into_iter()
+                .filter_map(|offset| project(focus + offset))
+                .map(|tick| tick.distance(center))
+                .fold(f32::INFINITY, f32::min);
+            if spacing >= self.min_spacing_pixels {
+                return interval * multiple;
+            }
+        }
+        interval * THINNING_STEPS[THINNING_STEPS.len() - 1]
+    }
+
+    /// Places a label on every visible tick near the camera target
+    pub fn update(
+        mut commands: Commands,
+        labels: Option<ResMut<AxisLabels>>,
+        layout: Res<GridLayout>,
+        camera_query: Query<(&Camera, &Transform, &CameraTarget)>,
+        mut text_query: Query<(&mut Text, &mut Style, &mut Visibility)>,
+    ) {
+        let Some(mut labels) = labels else {
+            return;
+        };
+        let Ok((camera, transform, camera_target)) = camera_query.get_single() else {
+            return;
+        };
+        let Some(viewport_size) = camera.logical_viewport_size() else {
+            return;
+        };
+
+        // global transforms are only propagated after the ui is laid out, the camera has no
+        // parent so its own transform is up to date
+        let camera_transform = GlobalTransform::from(*transform);
+        let project = |grid: Vec2| {
+            camera
+                .world_to_viewport(&camera_transform, layout.grid_to_world(grid))
+                .filter(|position| {
+                    position.cmpge(Vec2::ZERO).all() && position.cmple(viewport_size).all()
+                })
+        };
+
+        let focus = layout.world_to_grid(camera_target.get_look_at());
+        let step = labels.label_step(focus, project) as i32;
+
+        let mut visible: Vec<(String, Vec2, Color)> = Vec::new();
+        for (axis, color) in [(Vec2::X, labels.x_color), (Vec2::Y, labels.y_color)] {
+            let center = (focus.dot(axis) / step as f32).round() as i32;
+            let reach = labels.reach as i32;
+            for tick in center - reach..=center + reach {
+                // the origin is labeled once, by the x axis
+                if tick == 0 && axis == Vec2::Y {
+                    continue;
+                }
+
+                let value = tick.saturating_mul(step);
+                let corner = (axis * value as f32).as_ivec2();
+                let bounds = layout.bounds;
+                if corner.cmplt(bounds.min).any() || corner.cmpgt(bounds.max + IVec2::ONE).any() {
+                    continue;
+                }
+                if let Some(position) = project(axis * value as f32) {
+                    visible.push((value.to_string(), position, color));
+                }
+            }
+        }
+
+        for (index, (value, position, color)) in visible.iter().enumerate() {
+            // sit just below and to the right of the tick
+            let left = Val::Px(position.x + 4.0);
+            let top = Val::Px(position.y + 2.0);
+
+            let Some((mut text, mut style, mut visibility)) = labels
+                .labels
+                .get(index)
+                .and_then(|label| text_query.get_mut(*label).ok())
+            else {
+                let label = commands
+                    .spawn(
+                        TextBundle::from_section(
+                            value.clone(),
+                            TextStyle {
+                                font: labels.font.clone(),
+                                font_size: labels.font_size,
+                                color: *color,
+                            },
+                        )
+                        .with_style(Style {
+                            position_type: PositionType::Absolute,
+                            left,
+                            top,
+                            ..Default::default()
+                        }),
+                    )
+                    .id();
+                if index < labels.labels.len() {
+                    labels.labels[index] = label;
+                } else {
+                    labels.labels.push(label);
+                }
+                continue;
+            };
+
+            let section = &text.sections[0];
+            if section.value != *value
+                || section.style.color != *color
+                || section.style.font_size != labels.font_size
+            {
+                let section = &mut text.sections[0];
+                section.value.clone_from(value);
+                section.style.color = *color;
+                section.style.font_size = labels.font_size;
+            }
+            if style.left != left || style.top != top {
+                style.left = left;
+                style.top = top;
+            }
+            visibility.set_if_neq(Visibility::Inherited);
+        }
+
+        // labels left over from a busier frame wait hidden until they are needed again
+        for label in labels.labels.iter().skip(visible.len()) {
+            if let Ok((_, _, mut visibility)) = text_query.get_mut(*label) {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+}
@@ -0,0 +1,265 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+
+use crate::{
+    grid::{ExtendedGridMaterial, EMPTY_CELL_DATA_HANDLE},
+    grid_coord::{GridCoord, GridLayout},
+};
+
+pub struct CellFillPlugin;
+
+impl Plugin for CellFillPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(PostUpdate, CellFills::upload);
+    }
+}
+
+/// How a filled cell is painted
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellPattern {
+    #[default]
+    Solid,
+    /// diagonal stripes
+    Hatched,
+    /// stripes along both diagonals
+    CrossHatched,
+    /// a small grid of dots
+    Dotted,
+}
+
+impl CellPattern {
+    /// id the grid shader uses to pick the pattern
+    fn shader_id(&self) -> u32 {
+        match self {
+            CellPattern::Solid => 0,
+            CellPattern::Hatched => 1,
+            CellPattern::CrossHatched => 2,
+            CellPattern::Dotted => 3,
+        }
+    }
+}
+
+/// Paint applied to a single cell, the color's alpha controls how much of the paper shows through
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellFill {
+    pub color: Color,
+    pub pattern: CellPattern,
+}
+
+impl Default for CellFill {
+    fn default() -> Self {
+        Self {
+            color: Color::NONE,
+            pattern: CellPattern::Solid,
+        }
+    }
+}
+
+impl CellFill {
+    pub fn solid(color: Color) -> Self {
+        Self {
+            color,
+            pattern: CellPattern::Solid,
+        }
+    }
+
+    pub fn with_pattern(mut self, pattern: CellPattern) -> Self {
+        self.pattern = pattern;
+        self
+    }
+
+    /// packs the fill into a single Rg32Uint texel
+    fn texel(&self) -> [u8; 8] {
+        let mut texel = [0u8; 8];
+        texel[..4].copy_from_slice(&self.color.as_rgba_u8());
+        texel[4..].copy_from_slice(&self.pattern.shader_id().to_le_bytes());
+        texel
+    }
+}
+
+/// Fills for a rectangle of cells, drawn by the [`crate::grid::GridMaterial`] on the same entity
+///
+/// Changes are uploaded to the material's cell data texture at the end of the frame,
+/// so the fills can be repainted every frame. Changes made before the material has been added
+/// are held back until it is there.
+#[derive(Component, Debug, Clone)]
+pub struct CellFills {
+    /// cell stored in the first texel
+    origin: GridCoord,
+
+    /// number of cells covered along each axis
+    size: UVec2,
+
+    fills: Vec<CellFill>,
+
+    /// whether a change is waiting for the material to be added
+    upload_pending: bool,
+}
+
+impl CellFills {
+    pub fn new(origin: GridCoord, size: UVec2) -> Self {
+        Self {
+            origin,
+            size,
+            fills: vec![CellFill::default(); (size.x * size.y) as usize],
+            upload_pending: false,
+        }
+    }
+
+    /// fills covering every cell of the grid
+    pub fn covering(layout: &GridLayout) -> Self {
+        let size = (layout.bounds.max - layout.bounds.min + IVec2::ONE).as_uvec2();
+        Self::new(layout.bounds.min.into(), size)
+    }
+
+    pub fn origin(&self) -> GridCoord {
+        self.origin
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, cell: GridCoord) -> Option<usize> {
+        let texel = cell - self.origin;
+        if texel.x < 0
+            || texel.y < 0
+            || texel.x >= self.size.x as i32
+            || texel.y >= self.size.y as i32
+        {
+            return None;
+        }
+
+        Some((texel.y as u32 * self.size.x + texel.x as u32) as usize)
+    }
+
+    pub fn contains(&self, cell: GridCoord) -> bool {
+        self.index(cell).is_some()
+    }
+
+    pub fn get(&self, cell: GridCoord) -> Option<CellFill> {
+        self.index(cell).map(|index| self.fills[index])
+    }
+
+    /// paints a cell, returns false if the cell is outside of the fills
+    pub fn set(&mut self, cell: GridCoord, fill: CellFill) -> bool {
+        match self.index(cell) {
+            Some(index) => {
+                self.fills[index] = fill;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn clear(&mut self, cell: GridCoord) {
+        self.set(cell, CellFill::default());
+    }
+
+    pub fn clear_all(&mut self) {
+        self.fills.fill(CellFill::default());
+    }
+
+    fn to_image(&self) -> Image {
+        // a texture can't be empty, fills without cells upload a single blank texel
+        if self.fills.is_empty() {
+            return Image::new(
+                Extent3d::default(),
+                TextureDimension::D2,
+                CellFill::default().texel().to_vec(),
+                TextureFormat::Rg32Uint,
+            );
+        }
+
+        let data = self.fills.iter().flat_map(|fill| fill.texel()).collect();
+        Image::new(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::Rg32Uint,
+        )
+    }
+
+    /// Writes changed fills into the cell data texture of their grid material
+    pub fn upload(
+        mut fills_query: Query<(&mut CellFills, &Handle<ExtendedGridMaterial>)>,
+        mut materials: ResMut<Assets<ExtendedGridMaterial>>,
+        mut images: ResMut<Assets<Image>>,
+    ) {
+        for (mut fills, material) in fills_query.iter_mut() {
+            if !fills.is_changed() && !fills.upload_pending {
+                continue;
+            }
+
+            // the material has to be touched as well, otherwise it keeps binding the old texture.
+            // until it has been added the change is kept for later, without flagging the fills
+            // as changed again
+            let Some(material) = materials.get_mut(material) else {
+                fills.bypass_change_detection().upload_pending = true;
+                continue;
+            };
+            fills.bypass_change_detection().upload_pending = false;
+
+            let image = fills.to_image();
+            let cell_data = &mut material.extension.cell_data;
+            if *cell_data == EMPTY_CELL_DATA_HANDLE {
+                *cell_data = images.add(image);
+            } else {
+                images.insert(cell_data.clone(), image);
+            }
+            material.extension.cell_data_origin = fills.origin.as_ivec2();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::pbr::ExtendedMaterial;
+
+    use super::*;
+    use crate::grid::GridMaterial;
+
+    #[test]
+    fn fills_changed_before_their_material_is_added_are_uploaded_later() {
+        let mut world = World::new();
+        world.init_resource::<Assets<ExtendedGridMaterial>>();
+        world.init_resource::<Assets<Image>>();
+        let material: Handle<ExtendedGridMaterial> = world
+            .resource::<Assets<ExtendedGridMaterial>>()
+            .get_handle_provider()
+            .reserve_handle()
+            .typed();
+
+        let mut fills = CellFills::new(GridCoord::new(-2, 3), UVec2::new(4, 4));
+        fills.set(GridCoord::new(-1, 4), CellFill::solid(Color::RED));
+        world.spawn((fills, material.clone()));
+        // the same system runs twice, so the second run no longer sees the fills as changed
+        let mut upload = IntoSystem::into_system(CellFills::upload);
+        upload.initialize(&mut world);
+        upload.run((), &mut world);
+
+        world.resource_mut::<Assets<ExtendedGridMaterial>>().insert(
+            material.clone(),
+            ExtendedMaterial {
+                base: StandardMaterial::default(),
+                extension: GridMaterial::default(),
+            },
+        );
+        upload.run((), &mut world);
+
+        let materials = world.resource::<Assets<ExtendedGridMaterial>>();
+        let grid = &materials.get(&material).unwrap().extension;
+        assert_ne!(grid.cell_data, EMPTY_CELL_DATA_HANDLE);
+        assert_eq!(grid.cell_data_origin, IVec2::new(-2, 3));
+        assert!(world
+            .resource::<Assets<Image>>()
+            .get(&grid.cell_data)
+            .is_some());
+    }
+}
@@ -0,0 +1,306 @@
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{
+    grid::{ExtendedGridMaterial, GridHighlightRect, GridHighlights, MAX_GRID_HIGHLIGHTS},
+    grid_coord::{GridCoord, HoveredCell},
+};
+
+pub struct CellHighlightPlugin;
+
+impl Plugin for CellHighlightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SelectedCells>()
+            .add_systems(Update, SelectedCells::update.after(HoveredCell::update))
+            .add_systems(PostUpdate, CellHighlights::upload);
+    }
+}
+
+/// How a highlighted rectangle is drawn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// a line just inside the edge of the rectangle
+    #[default]
+    Outline,
+    /// paint over every cell
+    Fill,
+    /// an outline around a fill that fades in and out
+    Pulse,
+}
+
+impl HighlightKind {
+    /// id the grid shader uses to pick the style
+    fn shader_id(&self) -> u32 {
+        match self {
+            HighlightKind::Outline => 0,
+            HighlightKind::Fill => 1,
+            HighlightKind::Pulse => 2,
+        }
+    }
+}
+
+/// Look of a highlighted rectangle of cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HighlightStyle {
+    pub color: Color,
+    pub kind: HighlightKind,
+
+    /// width of outlines as a fraction of a single grid cell
+    pub outline_width: f32,
+}
+
+impl HighlightStyle {
+    pub fn outline(color: Color) -> Self {
+        Self {
+            color,
+            kind: HighlightKind::Outline,
+            outline_width: 0.08,
+        }
+    }
+
+    pub fn fill(color: Color) -> Self {
+        Self {
+            color,
+            kind: HighlightKind::Fill,
+            outline_width: 0.0,
+        }
+    }
+
+    pub fn pulse(color: Color) -> Self {
+        Self {
+            color,
+            kind: HighlightKind::Pulse,
+            outline_width: 0.08,
+        }
+    }
+
+    pub fn with_outline_width(mut self, outline_width: f32) -> Self {
+        self.outline_width = outline_width;
+        self
+    }
+}
+
+/// A rectangle of cells drawn with a [`HighlightStyle`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellHighlight {
+    pub min: GridCoord,
+
+    /// last cell of the rectangle, inclusive
+    pub max: GridCoord,
+
+    pub style: HighlightStyle,
+}
+
+impl CellHighlight {
+    pub fn cell(cell: GridCoord, style: HighlightStyle) -> Self {
+        Self::rect(cell, cell, style)
+    }
+
+    /// highlights every cell between a and b, inclusive
+    pub fn rect(a: GridCoord, b: GridCoord, style: HighlightStyle) -> Self {
+        let (a, b) = (a.as_ivec2(), b.as_ivec2());
+        Self {
+            min: a.min(b).into(),
+            max: a.max(b).into(),
+            style,
+        }
+    }
+
+    fn to_shader(self, group: u32) -> GridHighlightRect {
+        GridHighlightRect {
+            min: self.min.as_ivec2(),
+            max: self.max.as_ivec2(),
+            color: self.style.color,
+            style: self.style.kind.shader_id(),
+            outline_width: self.style.outline_width,
+            group,
+        }
+    }
+}
+
+/// highlight group of the selected cells, so the shader leaves out the edges between their rects
+const SELECTION_GROUP: u32 = 1;
+
+/// Highlights drawn by the [`crate::grid::GridMaterial`] on the same entity
+///
+/// Besides its own rectangles the grid marks the [`HoveredCell`] and the [`SelectedCells`].
+/// Only the first [`crate::grid::MAX_GRID_HIGHLIGHTS`] rectangles are drawn, the hovered cell
+/// takes priority followed by the selection.
+#[derive(Component, Debug, Clone, Default)]
+pub struct CellHighlights {
+    /// style of the cell under the cursor, None leaves it unmarked
+    pub hover: Option<HighlightStyle>,
+
+    /// style of the selected cells, None leaves them unmarked
+    pub selection: Option<HighlightStyle>,
+
+    pub highlights: Vec<CellHighlight>,
+}
+
+impl CellHighlights {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_hover(mut self, style: HighlightStyle) -> Self {
+        self.hover = Some(style);
+        self
+    }
+
+    pub fn with_selection(mut self, style: HighlightStyle) -> Self {
+        self.selection = Some(style);
+        self
+    }
+
+    pub fn add(&mut self, highlight: CellHighlight) {
+        self.highlights.push(highlight);
+    }
+
+    /// Every rectangle to draw, in the order the shader paints them
+    fn collect(&self, hovered: &HoveredCell, selected: &SelectedCells) -> GridHighlights {
+        // most important first, so the hovered cell is never the one left out
+        let mut rects = Vec::new();
+        if let Some((cell, style)) = hovered.cell.zip(self.hover) {
+            rects.push((CellHighlight::cell(cell, style), 0));
+        }
+        if let Some(style) = self.selection {
+            // the selection is outlined as one shape, however many rects it takes
+            rects.extend(
+                selected
+                    .rects()
+                    .into_iter()
+                    .map(|(min, max)| (CellHighlight::rect(min, max, style), SELECTION_GROUP)),
+            );
+        }
+        rects.extend(self.highlights.iter().map(|highlight| (*highlight, 0)));
+
+        // later rects are painted over earlier ones, so the most important go last
+        let mut shader_highlights = GridHighlights::default();
+        for (rect, group) in rects.into_iter().take(MAX_GRID_HIGHLIGHTS).rev() {
+            shader_highlights.push(rect.to_shader(group));
+        }
+        shader_highlights
+    }
+
+    /// Writes the highlights into their grid material whenever they, the hovered cell
+    /// or the selection change
+    pub fn upload(
+        highlights_query: Query<(Ref<CellHighlights>, &Handle<ExtendedGridMaterial>)>,
+        hovered: Res<HoveredCell>,
+        selected: Res<SelectedCells>,
+        mut materials: ResMut<Assets<ExtendedGridMaterial>>,
+    ) {
+        for (highlights, material) in highlights_query.iter() {
+            if !highlights.is_changed() && !hovered.is_changed() && !selected.is_changed() {
+                continue;
+            }
+            let Some(material) = materials.get_mut(material) else {
+                continue;
+            };
+
+            material.extension.highlights = highlights.collect(&hovered, &selected);
+        }
+    }
+}
+
+/// Cells picked with the mouse
+///
+/// Clicking a cell selects only that cell, shift clicking adds or removes it from the
+/// selection and escape clears it.
+#[derive(Resource, Debug, Default, Clone)]
+pub struct SelectedCells {
+    cells: HashSet<GridCoord>,
+}
+
+impl SelectedCells {
+    pub fn contains(&self, cell: GridCoord) -> bool {
+        self.cells.contains(&cell)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GridCoord> {
+        self.cells.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    pub fn insert(&mut self, cell: GridCoord) {
+        self.cells.insert(cell);
+    }
+
+    pub fn remove(&mut self, cell: GridCoord) {
+        self.cells.remove(&cell);
+    }
+
+    /// selects the cell if it isn't selected yet, otherwise deselects it
+    pub fn toggle(&mut self, cell: GridCoord) {
+        if !self.cells.remove(&cell) {
+            self.cells.insert(cell);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    /// the selection as rectangles of cells, first and last cell inclusive,
+    /// so neighbouring cells share a highlight
+    pub fn rects(&self) -> Vec<(GridCoord, GridCoord)> {
+        let mut cells: Vec<GridCoord> = self.cells.iter().copied().collect();
+        cells.sort_by_key(|cell| (cell.y, cell.x));
+
+        let mut runs: Vec<(GridCoord, GridCoord)> = Vec::new();
+        for cell in cells {
+            match runs.last_mut() {
+                Some((_, last)) if last.y == cell.y && last.x + 1 == cell.x => *last = cell,
+                _ => runs.push((cell, cell)),
+            }
+        }
+
+        // runs covering the same columns in consecutive rows are stacked into one rect
+        let mut rects: Vec<(GridCoord, GridCoord)> = Vec::new();
+        let mut columns: HashMap<(i32, i32), usize> = HashMap::new();
+        for (min, max) in runs {
+            match columns.get(&(min.x, max.x)) {
+                Some(&index) if rects[index].1.y + 1 == min.y => rects[index].1.y = max.y,
+                _ => {
+                    columns.insert((min.x, max.x), rects.len());
+                    rects.push((min, max));
+                }
+            }
+        }
+        rects
+    }
+
+    pub fn update(
+        mouse: Res<Input<MouseButton>>,
+        keys: Res<Input<KeyCode>>,
+        hovered: Res<HoveredCell>,
+        mut selected: ResMut<SelectedCells>,
+    ) {
+        if keys.just_pressed(KeyCode::Escape) && !selected.is_empty() {
+            selected.clear();
+        }
+
+        if !mouse.just_pressed(MouseButton::Left) {
+            return;
+        }
+        let Some(cell) = hovered.cell else {
+            return;
+        };
+
+        if keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            selected.toggle(cell);
+        } else {
+            selected.clear();
+            selected.insert(cell);
+        }
+    }
+}
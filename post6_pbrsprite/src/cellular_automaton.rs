@@ -0,0 +1,512 @@
+use std::{str::FromStr, sync::Arc};
+
+use bevy::prelude::*;
+use thiserror::Error;
+
+use crate::{
+    cell_fill::{CellFill, CellFills},
+    grid_coord::{GridCoord, HoveredCell},
+};
+
+pub struct CellularAutomatonPlugin;
+
+impl Plugin for CellularAutomatonPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (CellularAutomaton::control, CellularAutomaton::simulate)
+                .chain()
+                .after(HoveredCell::update),
+        );
+    }
+}
+
+/// most generations simulated in a single frame, so a slow frame doesn't snowball
+const MAX_STEPS_PER_FRAME: u32 = 8;
+
+#[derive(Debug, Error)]
+pub enum AutomatonRuleError {
+    #[error("rule {0:?} isn't written like B3/S23")]
+    Format(String),
+    #[error("{0:?} isn't a neighbour count, cells have 0 to 8 neighbours")]
+    Count(char),
+}
+
+/// Decides whether a cell is alive in the next generation
+#[derive(Clone)]
+pub enum AutomatonRule {
+    /// a dead cell is born when its number of live neighbours is set in the birth bits and a
+    /// live cell survives when the number is set in the survive bits, bit n counts n neighbours
+    LifeLike { birth: u16, survive: u16 },
+
+    /// takes whether the cell is alive and its number of live neighbours
+    Custom(Arc<dyn Fn(bool, u32) -> bool + Send + Sync>),
+}
+
+impl AutomatonRule {
+    /// Conway's Game of Life, B3/S23
+    pub const LIFE: AutomatonRule = AutomatonRule::LifeLike {
+        birth: 1 << 3,
+        survive: 1 << 2 | 1 << 3,
+    };
+
+    /// like Life but six neighbours give birth as well, B36/S23
+    pub const HIGH_LIFE: AutomatonRule = AutomatonRule::LifeLike {
+        birth: 1 << 3 | 1 << 6,
+        survive: 1 << 2 | 1 << 3,
+    };
+
+    /// every cell dies right away, B2/S
+    pub const SEEDS: AutomatonRule = AutomatonRule::LifeLike {
+        birth: 1 << 2,
+        survive: 0,
+    };
+
+    pub fn custom(rule: impl Fn(bool, u32) -> bool + Send + Sync + 'static) -> Self {
+        AutomatonRule::Custom(Arc::new(rule))
+    }
+
+    pub fn next(&self, alive: bool, neighbors: u32) -> bool {
+        match self {
+            AutomatonRule::LifeLike { birth, survive } => {
+                let counts = if alive { survive } else { birth };
+                counts & (1 << neighbors.min(8)) != 0
+            }
+            AutomatonRule::Custom(rule) => rule(alive, neighbors),
+        }
+    }
+}
+
+impl std::fmt::Debug for AutomatonRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AutomatonRule::LifeLike { birth, survive } => {
+                let counts = |bits: &u16| -> String {
+                    (0..=8)
+                        .filter(|count| bits & (1 << count) != 0)
+                        .map(|count| char::from(b'0' + count as u8))
+                        .collect()
+                };
+                write!(f, "B{}/S{}", counts(birth), counts(survive))
+            }
+            AutomatonRule::Custom(_) => f.write_str("Custom"),
+        }
+    }
+}
+
+/// Reads life-like rules in B/S notation such as B3/S23, either half may be left empty
+impl FromStr for AutomatonRule {
+    type Err = AutomatonRuleError;
+
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let format_error = || AutomatonRuleError::Format(rule.to_string());
+        let (birth, survive) = rule.trim().split_once('/').ok_or_else(format_error)?;
+        let birth = birth.strip_prefix(['B', 'b']).ok_or_else(format_error)?;
+        let survive = survive.strip_prefix(['S', 's']).ok_or_else(format_error)?;
+
+        let counts = |digits: &str| -> Result<u16, AutomatonRuleError> {
+            digits.chars().try_fold(0u16, |bits, digit| {
+                match digit.to_digit(10).filter(|count| *count <= 8) {
+                    Some(count) => Ok(bits | 1 << count),
+                    None => Err(AutomatonRuleError::Count(digit)),
+                }
+            })
+        };
+
+        Ok(AutomatonRule::LifeLike {
+            birth: counts(birth)?,
+            survive: counts(survive)?,
+        })
+    }
+}
+
+/// A cellular automaton running over a rectangle of cells, drawn into the [`CellFills`] on the
+/// same entity
+///
+/// Generations advance at a fixed rate however fast the frames come. Space pauses and resumes,
+/// period steps a single generation, backspace clears the cells and R scatters new ones.
+/// Dragging with the middle mouse button paints live cells, or erases them when the drag starts
+/// on a live cell. The automaton owns the fills of its rectangle and overwrites them.
+#[derive(Component, Debug, Clone)]
+pub struct CellularAutomaton {
+    /// first cell of the rectangle
+    origin: GridCoord,
+
+    /// number of cells along each axis
+    size: UVec2,
+
+    pub rule: AutomatonRule,
+
+    /// whether cells on an edge neighbour the cells on the opposite edge
+    pub wrap: bool,
+
+    /// generations per second while running
+    pub steps_per_second: f32,
+
+    pub paused: bool,
+
+    /// paint of the live cells, dead cells are left blank
+    pub alive_fill: CellFill,
+
+    cells: Vec<bool>,
+    generation: u64,
+
+    /// time since the last generation
+    elapsed: f32,
+
+    /// whether the cells changed since they were last drawn
+    dirty: bool,
+}
+
+impl CellularAutomaton {
+    /// a paused Game of Life with every cell dead
+    pub fn new(origin: GridCoord, size: UVec2) -> Self {
+        Self {
+            origin,
+            size,
+            rule: AutomatonRule::LIFE,
+            wrap: false,
+            steps_per_second: 8.0,
+            paused: true,
+            alive_fill: CellFill::solid(Color::rgba(0.08, 0.27, 0.72, 0.8)),
+            cells: vec![false; (size.x * size.y) as usize],
+            generation: 0,
+            elapsed: 0.0,
+            dirty: true,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: AutomatonRule) -> Self {
+        self.rule = rule;
+        self
+    }
+
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    pub fn with_speed(mut self, steps_per_second: f32) -> Self {
+        self.steps_per_second = steps_per_second;
+        self
+    }
+
+    pub fn with_fill(mut self, alive_fill: CellFill) -> Self {
+        self.alive_fill = alive_fill;
+        self
+    }
+
+    /// starts simulating as soon as it is spawned
+    pub fn running(mut self) -> Self {
+        self.paused = false;
+        self
+    }
+
+    pub fn origin(&self) -> GridCoord {
+        self.origin
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    /// number of generations simulated so far
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn index(&self, cell: GridCoord) -> Option<usize> {
+        let texel = cell - self.origin;
+        if texel.x < 0
+            || texel.y < 0
+            || texel.x >= self.size.x as i32
+            || texel.y >= self.size.y as i32
+        {
+            return None;
+        }
+
+        Some((texel.y as u32 * self.size.x + texel.x as u32) as usize)
+    }
+
+    pub fn contains(&self, cell: GridCoord) -> bool {
+        self.index(cell).is_some()
+    }
+
+    pub fn is_alive(&self, cell: GridCoord) -> bool {
+        self.index(cell).is_some_and(|index| self.cells[index])
+    }
+
+    /// brings a cell to life or kills it, returns false if the cell is outside the automaton
+    pub fn set(&mut self, cell: GridCoord, alive: bool) -> bool {
+        let Some(index) = self.index(cell) else {
+            return false;
+        };
+
+        if self.cells[index] != alive {
+            self.cells[index] = alive;
+            self.dirty = true;
+        }
+        true
+    }
+
+    /// sets cells relative to the origin from lines of text, where any of `O`, `o`, `*` or `#`
+    /// is alive. the first line lies along the origin's row
+    pub fn stamp(&mut self, offset: IVec2, pattern: &str) {
+        for (y, line) in pattern.lines().enumerate() {
+            for (x, char) in line.chars().enumerate() {
+                let cell = self.origin + (offset + IVec2::new(x as i32, y as i32)).into();
+                self.set(cell, matches!(char, 'O' | 'o' | '*' | '#'));
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.fill(false);
+        self.generation = 0;
+        self.dirty = true;
+    }
+
+    /// brings roughly density of the cells to life at random, the same seed scatters the
+    /// same cells
+    pub fn randomize(&mut self, seed: u64, density: f32) {
+        let mut state = seed;
+        for cell in self.cells.iter_mut() {
+            // splitmix64
+            state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^= z >> 31;
+            *cell = ((z >> 40) as f32 / (1u64 << 24) as f32) < density;
+        }
+        self.generation = 0;
+        self.dirty = true;
+    }
+
+    /// number of live cells among the eight around a cell
+    fn neighbors(&self, x: i32, y: i32) -> u32 {
+        let size = self.size.as_ivec2();
+        let mut counted = [(x, y); 9];
+        let mut count = 0;
+        let mut len = 1;
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let (mut nx, mut ny) = (x + dx, y + dy);
+                if self.wrap {
+                    nx = nx.rem_euclid(size.x);
+                    ny = ny.rem_euclid(size.y);
+                } else if nx < 0 || ny < 0 || nx >= size.x || ny >= size.y {
+                    continue;
+                }
+
+                // on boards less than 3 cells across, wrapping around lands back on the cell
+                // itself or on a neighbour that was already counted
+                if counted[..len].contains(&(nx, ny)) {
+                    continue;
+                }
+                counted[len] = (nx, ny);
+                len += 1;
+                count += self.cells[(ny * size.x + nx) as usize] as u32;
+            }
+        }
+        count
+    }
+
+    /// advances a single generation
+    pub fn step(&mut self) {
+        let size = self.size.as_ivec2();
+        let next: Vec<bool> = (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                let alive = self.cells[(y * size.x + x) as usize];
+                self.rule.next(alive, self.neighbors(x, y))
+            })
+            .collect();
+
+        if next != self.cells {
+            self.cells = next;
+            self.dirty = true;
+        }
+        self.generation += 1;
+    }
+
+    /// Pause, step, clear and scatter keys, and painting with the middle mouse button
+    pub fn control(
+        mut automaton_query: Query<&mut CellularAutomaton>,
+        keys: Res<Input<KeyCode>>,
+        mouse: Res<Input<MouseButton>>,
+        hovered: Res<HoveredCell>,
+        time: Res<Time>,
+        // whether the current drag paints live or dead cells
+        mut painting: Local<Option<bool>>,
+    ) {
+        if !mouse.pressed(MouseButton::Middle) {
+            *painting = None;
+        }
+
+        for mut automaton in automaton_query.iter_mut() {
+            if keys.just_pressed(KeyCode::Space) {
+                automaton.paused = !automaton.paused;
+            }
+            if keys.just_pressed(KeyCode::Period) {
+                automaton.paused = true;
+                automaton.step();
+            }
+            if keys.just_pressed(KeyCode::Back) {
+                automaton.clear();
+            }
+            if keys.just_pressed(KeyCode::R) {
+                automaton.randomize(time.elapsed().as_nanos() as u64, 0.3);
+            }
+
+            let Some(cell) = hovered.cell.filter(|cell| automaton.contains(*cell)) else {
+                continue;
+            };
+            if mouse.just_pressed(MouseButton::Middle) {
+                *painting = Some(!automaton.is_alive(cell));
+            }
+            if let Some(alive) = *painting {
+                automaton.set(cell, alive);
+            }
+        }
+    }
+
+    /// Runs the generations that are due and draws the cells that changed into the fills
+    pub fn simulate(
+        mut automaton_query: Query<(&mut CellularAutomaton, &mut CellFills)>,
+        time: Res<Time>,
+    ) {
+        for (mut automaton, mut fills) in automaton_query.iter_mut() {
+            if !automaton.paused && automaton.steps_per_second > 0.0 {
+                let interval = 1.0 / automaton.steps_per_second;
+                automaton.elapsed += time.delta_seconds();
+
+                let mut steps = 0;
+                while automaton.elapsed >= interval && steps < MAX_STEPS_PER_FRAME {
+                    automaton.elapsed -= interval;
+                    automaton.step();
+                    steps += 1;
+                }
+                // drop the generations that didn't fit into this frame
+                automaton.elapsed = automaton.elapsed.min(interval);
+            }
+
+            if !automaton.dirty {
+                continue;
+            }
+            automaton.dirty = false;
+
+            let size = automaton.size.as_ivec2();
+            for y in 0..size.y {
+                for x in 0..size.x {
+                    let cell = automaton.origin + IVec2::new(x, y).into();
+                    let fill = if automaton.cells[(y * size.x + x) as usize] {
+                        automaton.alive_fill
+                    } else {
+                        CellFill::default()
+                    };
+                    // only cells that look different are written, so the fills aren't flagged
+                    // as changed and uploaded when a generation leaves them as they were
+                    if fills.get(cell) != Some(fill) {
+                        fills.set(cell, fill);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules_parse_from_b_s_notation() {
+        let rule = |text: &str| format!("{:?}", text.parse::<AutomatonRule>().unwrap());
+        assert_eq!(rule("B3/S23"), format!("{:?}", AutomatonRule::LIFE));
+        assert_eq!(rule(" b36/s23 "), format!("{:?}", AutomatonRule::HIGH_LIFE));
+        assert_eq!(rule("B2/S"), format!("{:?}", AutomatonRule::SEEDS));
+        assert_eq!(rule("B/S012345678"), "B/S012345678");
+    }
+
+    #[test]
+    fn malformed_rules_are_rejected() {
+        for text in ["B3S23", "3/23", "B3/23", "S23/B3", ""] {
+            let err = text.parse::<AutomatonRule>().unwrap_err();
+            assert!(matches!(err, AutomatonRuleError::Format(_)), "{text:?}");
+        }
+        let err = "B39/S23".parse::<AutomatonRule>().unwrap_err();
+        assert!(matches!(err, AutomatonRuleError::Count('9')));
+        let err = "B3/S2x".parse::<AutomatonRule>().unwrap_err();
+        assert!(matches!(err, AutomatonRuleError::Count('x')));
+    }
+
+    fn alive_cells(automaton: &CellularAutomaton) -> Vec<(i32, i32)> {
+        let size = automaton.size().as_ivec2();
+        (0..size.y)
+            .flat_map(|y| (0..size.x).map(move |x| (x, y)))
+            .filter(|(x, y)| automaton.is_alive(automaton.origin() + IVec2::new(*x, *y).into()))
+            .collect()
+    }
+
+    #[test]
+    fn blinker_flips_back_after_two_generations() {
+        let mut automaton = CellularAutomaton::new(GridCoord::new(-3, 7), UVec2::new(5, 5));
+        automaton.stamp(IVec2::new(2, 1), "O\nO\nO");
+        let vertical = alive_cells(&automaton);
+        assert_eq!(vertical, [(2, 1), (2, 2), (2, 3)]);
+
+        automaton.step();
+        assert_eq!(alive_cells(&automaton), [(1, 2), (2, 2), (3, 2)]);
+        automaton.step();
+        assert_eq!(alive_cells(&automaton), vertical);
+        assert_eq!(automaton.generation(), 2);
+    }
+
+    #[test]
+    fn narrow_wrapped_boards_count_each_neighbour_once() {
+        // on a 1 cell wide board every wrapped neighbour would be the cell itself
+        let mut automaton =
+            CellularAutomaton::new(GridCoord::ZERO, UVec2::new(1, 1)).with_wrap(true);
+        automaton.set(GridCoord::ZERO, true);
+        assert_eq!(automaton.neighbors(0, 0), 0);
+
+        // on a 2x2 board the other three cells are each a neighbour once
+        let mut automaton =
+            CellularAutomaton::new(GridCoord::ZERO, UVec2::new(2, 2)).with_wrap(true);
+        automaton.stamp(IVec2::ZERO, "OO\nOO");
+        assert_eq!(automaton.neighbors(0, 0), 3);
+        assert_eq!(automaton.neighbors(1, 1), 3);
+    }
+
+    #[test]
+    fn unchanged_cells_leave_the_fills_alone() {
+        let mut world = World::new();
+        world.init_resource::<Time>();
+        let automaton = CellularAutomaton::new(GridCoord::new(2, -4), UVec2::new(6, 6));
+        let fills = CellFills::new(GridCoord::new(2, -4), UVec2::new(6, 6));
+        let entity = world.spawn((automaton, fills)).id();
+
+        let mut simulate = IntoSystem::into_system(CellularAutomaton::simulate);
+        simulate.initialize(&mut world);
+        simulate.run((), &mut world);
+        world.clear_trackers();
+
+        // clearing an empty board redraws it without anything to write
+        let mut automaton = world.get_mut::<CellularAutomaton>(entity).unwrap();
+        automaton.clear();
+        simulate.run((), &mut world);
+        let fills = world.entity(entity).get_ref::<CellFills>().unwrap();
+        assert!(!fills.is_changed());
+        world.clear_trackers();
+
+        let cell = GridCoord::new(4, -1);
+        let mut automaton = world.get_mut::<CellularAutomaton>(entity).unwrap();
+        automaton.set(cell, true);
+        let alive_fill = automaton.alive_fill;
+        simulate.run((), &mut world);
+        let fills = world.entity(entity).get_ref::<CellFills>().unwrap();
+        assert!(fills.is_changed());
+        assert_eq!(fills.get(cell), Some(alive_fill));
+    }
+}
@@ -0,0 +1,531 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    pbr::ExtendedMaterial,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    utils::{BoxedFuture, HashSet},
+};
+use thiserror::Error;
+
+use crate::{
+    grid::{ExtendedGridMaterial, GridBox, GridMapping, GridMaterial, GridTriplanar},
+    grid_coord::GridLayout,
+    pbr_sprite::{PaperSprite, PbrPaperMaterial, QuadSprite},
+    plot::Plot,
+};
+
+pub struct ChartPlugin;
+
+impl Plugin for ChartPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ChartData>()
+            .init_asset_loader::<ChartDataLoader>()
+            .add_systems(Update, Chart::build);
+    }
+}
+
+/// A table read from a CSV file, the first row names the columns
+///
+/// Fields are separated by commas and may be quoted, a quote inside a quoted field is
+/// written twice. Only spaces may follow the closing quote of a field. Empty lines are skipped.
+#[derive(Asset, TypePath, Debug, Clone, Default)]
+pub struct ChartData {
+    pub headers: Vec<String>,
+
+    /// every row has a field for each header
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ChartData {
+    pub fn parse(text: &str) -> Result<Self, ChartDataLoaderError> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| (index + 1, line));
+
+        let Some((line, header)) = lines.next() else {
+            return Ok(Self::default());
+        };
+        let headers: Vec<String> = parse_record(header, line)?
+            .into_iter()
+            .map(|header| header.trim().to_string())
+            .collect();
+
+        let mut rows = Vec::new();
+        for (line, record) in lines {
+            let row = parse_record(record, line)?;
+            if row.len() != headers.len() {
+                return Err(ChartDataLoaderError::RowLength {
+                    line,
+                    expected: headers.len(),
+                    found: row.len(),
+                });
+            }
+            rows.push(row);
+        }
+
+        Ok(Self { headers, rows })
+    }
+
+    /// index of the column with the given header
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|header| header == name)
+    }
+
+    /// numbers in a column row by row, None where a field isn't a number
+    pub fn values(&self, column: usize) -> Vec<Option<f32>> {
+        self.rows
+            .iter()
+            .map(|row| {
+                row.get(column)
+                    .and_then(|field| field.trim().parse::<f32>().ok())
+                    .filter(|value| value.is_finite())
+            })
+            .collect()
+    }
+}
+
+/// Splits a single line of a CSV file into its fields
+fn parse_record(record: &str, line: usize) -> Result<Vec<String>, ChartDataLoaderError> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    // set once a quoted field is closed, until the comma ending it
+    let mut closed = false;
+    let mut chars = record.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    field.push('"');
+                    chars.next();
+                } else {
+                    quoted = false;
+                    closed = true;
+                }
+            }
+            '"' if !closed && field.trim().is_empty() => {
+                field.clear();
+                quoted = true;
+            }
+            ',' if !quoted => {
+                fields.push(std::mem::take(&mut field));
+                closed = false;
+            }
+            _ if closed && char.is_whitespace() => {}
+            _ if closed => return Err(ChartDataLoaderError::TextAfterQuote(line)),
+            _ => field.push(char),
+        }
+    }
+    if quoted {
+        return Err(ChartDataLoaderError::UnterminatedQuote(line));
+    }
+    fields.push(field);
+
+    Ok(fields)
+}
+
+#[derive(Debug, Error)]
+pub enum ChartDataLoaderError {
+    #[error("could not read chart data: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("chart data is not valid utf-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("row on line {line} has {found} fields but there are {expected} columns")]
+    RowLength {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+    #[error("quoted field on line {0} is never closed")]
+    UnterminatedQuote(usize),
+    #[error("quoted field on line {0} is followed by text before the next comma")]
+    TextAfterQuote(usize),
+}
+
+/// Loads [`ChartData`] from CSV files
+#[derive(Default)]
+pub struct ChartDataLoader;
+
+impl AssetLoader for ChartDataLoader {
+    type Asset = ChartData;
+    type Settings = ();
+    type Error = ChartDataLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<ChartData, ChartDataLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            ChartData::parse(&String::from_utf8(bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["csv"]
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ChartError {
+    #[error("chart data has no column named {0:?}")]
+    MissingColumn(String),
+}
+
+/// How a [`Chart`] draws its data
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChartKind {
+    /// a paper sprite standing on every point
+    #[default]
+    Scatter,
+
+    /// an ink line through the points in the order of the rows
+    Line,
+
+    /// a paper box rising from the grid at every x, as tall as its y value.
+    /// negative values sink below the paper
+    Bar,
+}
+
+/// How data units are scaled into grid cells
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChartScale {
+    /// fits the data and the origin into this many cells along x and y, rounded up to 1, 2
+    /// or 5 times a power of ten units per cell so the lines fall on round numbers
+    Fit(Vec2),
+
+    /// data units per cell along x and y
+    UnitsPerCell(Vec2),
+}
+
+/// Smallest of 1, 2 or 5 times a power of ten that is at least value
+fn round_step(value: f32) -> f32 {
+    if !value.is_finite() || value <= 0.0 {
+        return 1.0;
+    }
+
+    let power = 10.0_f32.powf(value.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|multiple| multiple * power)
+        .find(|step| *step >= value * (1.0 - 1e-6))
+        .unwrap_or(10.0 * power)
+}
+
+/// Two columns of a [`ChartData`] drawn onto the grid described by the [`GridLayout`]
+///
+/// The chart's pieces are spawned as children of its entity whenever the chart, its data or
+/// the layout change. They are laid out in world space, so the entity's transform should stay
+/// at identity, a [`SpatialBundle`] gives the children somewhere to inherit it from.
+#[derive(Component, Debug, Clone)]
+pub struct Chart {
+    pub data: Handle<ChartData>,
+    pub kind: ChartKind,
+
+    /// header of the column holding the x values, each row's index is used when None
+    pub x_column: Option<String>,
+
+    /// header of the column holding the y values
+    pub y_column: String,
+
+    /// grid position where x and y are both 0, in cells
+    pub origin: Vec2,
+
+    pub scale: ChartScale,
+
+    pub color: Color,
+
+    /// width of the bars, size of the scatter points or thickness of the line, in cells
+    pub size: f32,
+}
+
+impl Chart {
+    pub fn new(kind: ChartKind, data: Handle<ChartData>, y_column: impl Into<String>) -> Self {
+        Self {
+            data,
+            kind,
+            x_column: None,
+            y_column: y_column.into(),
+            origin: Vec2::ZERO,
+            scale: ChartScale::Fit(Vec2::splat(10.0)),
+            color: Color::rgba(0.08, 0.27, 0.72, 1.0),
+            size: match kind {
+                ChartKind::Scatter => 0.6,
+                ChartKind::Line => 0.1,
+                ChartKind::Bar => 0.8,
+            },
+        }
+    }
+
+    pub fn with_x_column(mut self, x_column: impl Into<String>) -> Self {
+        self.x_column = Some(x_column.into());
+        self
+    }
+
+    pub fn with_origin(mut self, origin: Vec2) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: ChartScale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_size(mut self, size: f32) -> Self {
+        self.size = size;
+        self
+    }
+
+    /// x and y of every row where both are numbers, in data units
+    pub fn points(&self, data: &ChartData) -> Result<Vec<Vec2>, ChartError> {
+        let column = |name: &String| {
+            data.column(name)
+                .map(|column| data.values(column))
+                .ok_or_else(|| ChartError::MissingColumn(name.clone()))
+        };
+        let y_values = column(&self.y_column)?;
+        let x_values = match &self.x_column {
+            Some(x_column) => column(x_column)?,
+            None => (0..data.rows.len()).map(|row| Some(row as f32)).collect(),
+        };
+
+        Ok(x_values
+            .into_iter()
+            .zip(y_values)
+            .filter_map(|(x, y)| Some(Vec2::new(x?, y?)))
+            .collect())
+    }
+
+    /// data units per cell along x and y
+    pub fn units_per_cell(&self, points: &[Vec2]) -> Vec2 {
+        match self.scale {
+            ChartScale::UnitsPerCell(units) => units,
+            ChartScale::Fit(cells) => {
+                let min = points.iter().fold(Vec2::ZERO, |min, point| min.min(*point));
+                let max = points.iter().fold(Vec2::ZERO, |max, point| max.max(*point));
+                let units = (max - min) / cells.max(Vec2::splat(1e-6));
+                Vec2::new(round_step(units.x), round_step(units.y))
+            }
+        }
+    }
+
+    /// Spawns the pieces of new and changed charts, and of charts whose data loaded or changed
+    #[allow(clippy::too_many_arguments)]
+    pub fn build(
+        mut commands: Commands,
+        mut events: EventReader<AssetEvent<ChartData>>,
+        chart_data: Res<Assets<ChartData>>,
+        chart_query: Query<(Entity, Ref<Chart>)>,
+        layout: Res<GridLayout>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut grid_materials: ResMut<Assets<ExtendedGridMaterial>>,
+        mut sprite_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, PbrPaperMaterial>>>,
+        mut images: ResMut<Assets<Image>>,
+    ) {
+        let changed: HashSet<AssetId<ChartData>> = events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                    Some(*id)
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (entity, chart) in chart_query.iter() {
+            if !chart.is_changed() && !layout.is_changed() && !changed.contains(&chart.data.id()) {
+                continue;
+            }
+            let Some(data) = chart_data.get(&chart.data) else {
+                continue;
+            };
+
+            commands.entity(entity).despawn_descendants();
+            let points = match chart.points(data) {
+                Ok(points) => points,
+                Err(err) => {
+                    warn!("could not build chart: {err}");
+                    continue;
+                }
+            };
+
+            // data points in grid cells
+            let units = chart.units_per_cell(&points);
+            let cells: Vec<Vec2> = points
+                .iter()
+                .map(|point| chart.origin + *point / units)
+                .collect();
+            let cell_size = layout.cell_size.min_element();
+
+            match chart.kind {
+                ChartKind::Scatter => {
+                    let size = chart.size * cell_size;
+                    let mesh = meshes.add(PaperSprite(QuadSprite::new(Vec2::splat(size))).into());
+                    let material = sprite_materials.add(ExtendedMaterial {
+                        base: StandardMaterial {
+                            base_color_texture: Some(images.add(marker_image(chart.color))),
+                            alpha_mode: AlphaMode::Mask(0.2),
+                            ..Default::default()
+                        },
+                        extension: PbrPaperMaterial {
+                            uv_scale: Vec2::ONE,
+                            uv_translate: Vec2::ZERO,
+                            outline_thickness: 0.08,
+                            outline_color: Color::WHITE,
+                        },
+                    });
+
+                    for cell in cells {
+                        // the sprites stand on the grid, facing along grid +y
+                        let position = layout.grid_to_world(cell) + layout.normal() * size * 0.5;
+                        commands
+                            .spawn(MaterialMeshBundle {
+                                mesh: mesh.clone(),
+                                material: material.clone(),
+                                transform: Transform::from_translation(position)
+                                    .with_rotation(layout.rotation),
+                                ..Default::default()
+                            })
+                            .set_parent(entity);
+                    }
+                }
+                ChartKind::Line => {
+                    if cells.len() < 2 {
+                        continue;
+                    }
+
+                    let plot = Plot::polyline(cells)
+                        .with_color(chart.color)
+                        .with_thickness(chart.size);
+                    commands.spawn(plot).set_parent(entity);
+                }
+                ChartKind::Bar => {
+                    // the faces of the bars show the same cells as the grid they stand on
+                    let material = grid_materials.add(ExtendedGridMaterial {
+                        base: chart.color.into(),
+                        extension: GridMaterial {
+                            color: Color::rgba(0.0, 0.0, 0.0, 0.25),
+                            line_widths: Vec2::new(0.02, 0.02),
+                            mapping: GridMapping::Triplanar,
+                            triplanar: GridTriplanar::new(cell_size).with_origin(layout.origin),
+                            ..Default::default()
+                        },
+                    });
+
+                    let width = chart.size * cell_size;
+                    for (cell, point) in cells.into_iter().zip(points) {
+                        let height = point.y / units.y * cell_size;
+                        if height == 0.0 {
+                            continue;
+                        }
+
+                        // bars stand on the x axis of the chart
+                        let foot = Vec2::new(cell.x, chart.origin.y);
+                        let position = layout.grid_to_world(foot) + layout.normal() * height * 0.5;
+                        let bar = GridBox::new(Vec3::new(width, height.abs(), width), UVec3::ZERO);
+                        commands
+                            .spawn(MaterialMeshBundle {
+                                mesh: meshes.add(bar.into()),
+                                material: material.clone(),
+                                transform: Transform::from_translation(position)
+                                    .with_rotation(layout.rotation),
+                                ..Default::default()
+                            })
+                            .set_parent(entity);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Round marker drawn on scatter point sprites, the edge is left clear for the outline
+fn marker_image(color: Color) -> Image {
+    const SIZE: u32 = 32;
+    let [r, g, b, _] = color.as_rgba_u8();
+    let center = Vec2::splat(SIZE as f32 * 0.5);
+    let radius = SIZE as f32 * 0.38;
+
+    let data: Vec<u8> = (0..SIZE * SIZE)
+        .flat_map(|index| {
+            let texel = Vec2::new((index % SIZE) as f32, (index / SIZE) as f32) + 0.5;
+            let alpha = (radius + 0.5 - texel.distance(center)).clamp(0.0, 1.0);
+            [r, g, b, (alpha * 255.0) as u8]
+        })
+        .collect();
+
+    Image::new(
+        Extent3d {
+            width: SIZE,
+            height: SIZE,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quoted_fields_keep_their_commas() {
+        let fields = parse_record(r#"1,"a, b", " c ",d"#, 1).unwrap();
+        assert_eq!(fields, ["1", "a, b", " c ", "d"]);
+    }
+
+    #[test]
+    fn doubled_quotes_are_one_quote() {
+        let fields = parse_record(r#""say ""hi""","""""#, 1).unwrap();
+        assert_eq!(fields, [r#"say "hi""#, r#"""#]);
+    }
+
+    #[test]
+    fn unterminated_quotes_are_rejected() {
+        let err = parse_record(r#"1,"open, still open"#, 4).unwrap_err();
+        assert!(matches!(err, ChartDataLoaderError::UnterminatedQuote(4)));
+    }
+
+    #[test]
+    fn text_after_a_closing_quote_is_rejected() {
+        let err = parse_record(r#""a" b,c"#, 2).unwrap_err();
+        assert!(matches!(err, ChartDataLoaderError::TextAfterQuote(2)));
+
+        // spaces before the comma are fine
+        assert_eq!(parse_record(r#""a"  ,c"#, 2).unwrap(), ["a", "c"]);
+    }
+
+    #[test]
+    fn ragged_rows_are_rejected() {
+        let err = ChartData::parse("x,y\n1,2\n\n3\n").unwrap_err();
+        assert!(matches!(
+            err,
+            ChartDataLoaderError::RowLength {
+                line: 4,
+                expected: 2,
+                found: 1,
+            }
+        ));
+    }
+
+    #[test]
+    fn crlf_line_endings_are_stripped() {
+        let data = ChartData::parse("x, y\r\n1,\"2\"\r\n\r\n3,4\r\n").unwrap();
+        assert_eq!(data.headers, ["x", "y"]);
+        assert_eq!(data.rows, [["1", "2"], ["3", "4"]]);
+        assert_eq!(data.values(1), [Some(2.0), Some(4.0)]);
+    }
+}
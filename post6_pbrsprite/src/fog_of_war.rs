@@ -0,0 +1,312 @@
+use bevy::{
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+    transform::TransformSystem,
+};
+
+use crate::{
+    grid::{ExtendedGridMaterial, EMPTY_FOG_DATA_HANDLE},
+    grid_coord::{GridCoord, GridLayout},
+    grid_nav::NavGrid,
+};
+
+pub struct FogOfWarPlugin;
+
+impl Plugin for FogOfWarPlugin {
+    fn build(&self, app: &mut App) {
+        // vision follows the sources to wherever they ended up this frame
+        app.add_systems(
+            PostUpdate,
+            (FogOfWar::update, FogOfWar::upload)
+                .chain()
+                .after(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// How much of a cell has been seen
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FogState {
+    /// never seen, drawn as blank paper
+    #[default]
+    Unexplored,
+    /// seen before but out of sight now, drawn faded
+    Explored,
+    /// in sight of a vision source
+    Visible,
+}
+
+impl FogState {
+    /// id the grid shader uses for the state
+    pub fn shader_id(&self) -> u32 {
+        match self {
+            FogState::Unexplored => 0,
+            FogState::Explored => 1,
+            FogState::Visible => 2,
+        }
+    }
+}
+
+/// Lets the [`FogOfWar`] see the cells around an entity
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct VisionSource {
+    /// distance in cells from the entity's cell to the centers of the cells it can see
+    pub radius: f32,
+}
+
+impl VisionSource {
+    pub fn new(radius: f32) -> Self {
+        Self { radius }
+    }
+}
+
+/// What has been seen of a rectangle of cells, drawn by the [`crate::grid::GridMaterial`] on the
+/// same entity
+///
+/// Every frame the cells in sight of a [`VisionSource`] turn visible and the ones that dropped
+/// out of sight are left explored. Blocked cells of the [`NavGrid`], when there is one, hide
+/// whatever lies behind them. Changes are uploaded to the material's fog texture at the end
+/// of the frame, or once the material has been added.
+#[derive(Component, Debug, Clone)]
+pub struct FogOfWar {
+    /// cell stored in the first texel
+    origin: GridCoord,
+
+    /// number of cells covered along each axis
+    size: UVec2,
+
+    /// state of the cells outside the rectangle
+    pub outside: FogState,
+
+    states: Vec<FogState>,
+
+    /// whether a change is waiting for the material to be added
+    upload_pending: bool,
+}
+
+impl FogOfWar {
+    /// fog over a rectangle of unexplored cells, the cells around it stay unexplored as well
+    pub fn new(origin: GridCoord, size: UVec2) -> Self {
+        Self {
+            origin,
+            size,
+            outside: FogState::Unexplored,
+            states: vec![FogState::Unexplored; (size.x * size.y) as usize],
+            upload_pending: false,
+        }
+    }
+
+    /// fog covering every cell of the grid
+    pub fn covering(layout: &GridLayout) -> Self {
+        let size = (layout.bounds.max - layout.bounds.min + IVec2::ONE).as_uvec2();
+        Self::new(layout.bounds.min.into(), size)
+    }
+
+    pub fn with_outside(mut self, outside: FogState) -> Self {
+        self.outside = outside;
+        self
+    }
+
+    pub fn origin(&self) -> GridCoord {
+        self.origin
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn index(&self, cell: GridCoord) -> Option<usize> {
+        let texel = cell - self.origin;
+        if texel.x < 0
+            || texel.y < 0
+            || texel.x >= self.size.x as i32
+            || texel.y >= self.size.y as i32
+        {
+            return None;
+        }
+
+        Some((texel.y as u32 * self.size.x + texel.x as u32) as usize)
+    }
+
+    pub fn get(&self, cell: GridCoord) -> FogState {
+        self.index(cell)
+            .map_or(self.outside, |index| self.states[index])
+    }
+
+    /// changes the state of a cell, returns false if the cell is outside of the fog
+    pub fn set(&mut self, cell: GridCoord, state: FogState) -> bool {
+        match self.index(cell) {
+            Some(index) => {
+                self.states[index] = state;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// forgets everything that has been seen
+    pub fn reset(&mut self) {
+        self.states.fill(FogState::Unexplored);
+    }
+
+    /// Sees the cells around every vision source, what they no longer see is left explored
+    pub fn update(
+        mut fog_query: Query<&mut FogOfWar>,
+        source_query: Query<(&VisionSource, &GlobalTransform)>,
+        layout: Res<GridLayout>,
+        nav_grid: Option<Res<NavGrid>>,
+    ) {
+        for mut fog in fog_query.iter_mut() {
+            let mut states = fog.states.clone();
+            for state in states.iter_mut() {
+                if *state == FogState::Visible {
+                    *state = FogState::Explored;
+                }
+            }
+
+            for (source, transform) in source_query.iter() {
+                let center = layout.world_to_cell(transform.translation());
+                let reach = source.radius.max(0.0).ceil() as i32;
+                for y in -reach..=reach {
+                    for x in -reach..=reach {
+                        let offset = IVec2::new(x, y);
+                        if offset.as_vec2().length() > source.radius {
+                            continue;
+                        }
+
+                        let cell = center + offset.into();
+                        let Some(index) = fog.index(cell) else {
+                            continue;
+                        };
+                        let in_sight = nav_grid
+                            .as_ref()
+                            .is_none_or(|nav_grid| nav_grid.line_of_sight(center, cell));
+                        if in_sight {
+                            states[index] = FogState::Visible;
+                        }
+                    }
+                }
+            }
+
+            // only touch the fog when something changed, so it isn't uploaded every frame
+            if states != fog.states {
+                fog.states = states;
+            }
+        }
+    }
+
+    /// texture bound by grid materials without fog of war, a single visible cell
+    pub(crate) fn clear_image() -> Image {
+        Self::new(GridCoord::ZERO, UVec2::ZERO)
+            .with_outside(FogState::Visible)
+            .to_image()
+    }
+
+    fn to_image(&self) -> Image {
+        // a texture can't be empty, fog without cells uploads a single texel that looks like
+        // the cells outside of it
+        if self.states.is_empty() {
+            return Image::new(
+                Extent3d::default(),
+                TextureDimension::D2,
+                vec![self.outside.shader_id() as u8],
+                TextureFormat::R8Uint,
+            );
+        }
+
+        let data = self
+            .states
+            .iter()
+            .map(|state| state.shader_id() as u8)
+            .collect();
+        Image::new(
+            Extent3d {
+                width: self.size.x,
+                height: self.size.y,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            data,
+            TextureFormat::R8Uint,
+        )
+    }
+
+    /// Writes changed fog into the fog texture of its grid material
+    pub fn upload(
+        mut fog_query: Query<(&mut FogOfWar, &Handle<ExtendedGridMaterial>)>,
+        mut materials: ResMut<Assets<ExtendedGridMaterial>>,
+        mut images: ResMut<Assets<Image>>,
+    ) {
+        for (mut fog, material) in fog_query.iter_mut() {
+            if !fog.is_changed() && !fog.upload_pending {
+                continue;
+            }
+
+            // like cell fills, the material has to be touched to bind the new texture and the
+            // change waits for the material when it hasn't been added yet
+            let Some(material) = materials.get_mut(material) else {
+                fog.bypass_change_detection().upload_pending = true;
+                continue;
+            };
+            fog.bypass_change_detection().upload_pending = false;
+
+            let image = fog.to_image();
+            let fog_data = &mut material.extension.fog_data;
+            if *fog_data == EMPTY_FOG_DATA_HANDLE {
+                *fog_data = images.add(image);
+            } else {
+                images.insert(fog_data.clone(), image);
+            }
+            material.extension.fog.origin = fog.origin.as_ivec2();
+            material.extension.fog.outside = fog.outside.shader_id();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{GridFog, GridMaterial};
+
+    /// state the grid shader's cell_fog reads for a cell
+    fn shader_state(image: &Image, fog: &GridFog, cell: IVec2) -> u32 {
+        let texel = cell - fog.origin;
+        let size = image.size().as_ivec2();
+        if texel.cmpge(IVec2::ZERO).all() && texel.cmplt(size).all() {
+            image.data[(texel.y * size.x + texel.x) as usize] as u32
+        } else {
+            fog.outside
+        }
+    }
+
+    #[test]
+    fn materials_without_fog_see_every_cell() {
+        let material = GridMaterial::default();
+        assert_eq!(material.fog_data, EMPTY_FOG_DATA_HANDLE);
+
+        // the texel of the blank texture sits on cell (0, 0)
+        let image = FogOfWar::clear_image();
+        for y in -2..=2 {
+            for x in -2..=2 {
+                let state = shader_state(&image, &material.fog, IVec2::new(x, y));
+                assert_eq!(state, FogState::Visible.shader_id(), "cell ({x}, {y})");
+            }
+        }
+    }
+
+    #[test]
+    fn empty_fog_looks_like_the_cells_outside() {
+        let fog =
+            FogOfWar::new(GridCoord::new(-3, 4), UVec2::new(0, 5)).with_outside(FogState::Explored);
+        let image = fog.to_image();
+        let shader_fog = GridFog {
+            origin: fog.origin.as_ivec2(),
+            outside: fog.outside.shader_id(),
+            ..Default::default()
+        };
+        for cell in [IVec2::new(-3, 4), IVec2::ZERO] {
+            let state = shader_state(&image, &shader_fog, cell);
+            assert_eq!(state, FogState::Explored.shader_id());
+        }
+    }
+}
@@ -1,29 +1,71 @@
-use std::f32::consts::PI;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+use crate::{
+    fog_of_war::FogOfWar,
+    grid_coord::GridLayout,
+    hex_coord::{HexCoord, HexOrientation},
+};
 
 use bevy::{
     input::mouse::MouseWheel,
-    pbr::{ExtendedMaterial, MaterialExtension},
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
     prelude::*,
     render::{
-        mesh::Indices,
-        render_resource::{AsBindGroup, PrimitiveTopology, ShaderRef},
+        mesh::{Indices, MeshVertexBufferLayout},
+        render_resource::{
+            AsBindGroup, Extent3d, PrimitiveTopology, RenderPipelineDescriptor, ShaderRef,
+            SpecializedMeshPipelineError, TextureDimension, TextureFormat,
+        },
     },
 };
+use serde::{Deserialize, Serialize};
 
 pub struct GridPlugin;
 
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
-        let mut material_plugin =
-            MaterialPlugin::<ExtendedMaterial<StandardMaterial, GridMaterial>>::default();
-        material_plugin.prepass_enabled = false;
+        let material_plugin = MaterialPlugin::<ExtendedMaterial<StandardMaterial, GridMaterial>> {
+            prepass_enabled: true,
+            ..Default::default()
+        };
 
         app.add_plugins(material_plugin)
-            .add_systems(Update, CameraTarget::update);
+            .add_systems(Update, (CameraTarget::update, GridMaterial::advance_scroll));
+
+        // shaders imported by their #define_import_path aren't loaded on their own, so the
+        // grid shaders' shared module is loaded up front and kept around
+        let sheet_shader = app.world.resource::<AssetServer>().load("grid_sheet.wgsl");
+        app.insert_resource(GridShaderModules(vec![sheet_shader]));
+
+        // grid materials without cell fills bind this texture, which holds a single empty cell
+        let mut images = app.world.resource_mut::<Assets<Image>>();
+        images.insert(
+            EMPTY_CELL_DATA_HANDLE,
+            Image::new_fill(
+                Extent3d::default(),
+                TextureDimension::D2,
+                &[0u8; 8],
+                TextureFormat::Rg32Uint,
+            ),
+        );
+        // and grid materials without fog of war bind this one, a single visible cell that
+        // leaves the fog off
+        images.insert(EMPTY_FOG_DATA_HANDLE, FogOfWar::clear_image());
     }
 }
 
+/// Keeps the shader modules imported by the grid shaders loaded
+#[derive(Resource)]
+struct GridShaderModules(#[allow(dead_code)] Vec<Handle<Shader>>);
+
+pub const EMPTY_CELL_DATA_HANDLE: Handle<Image> = Handle::weak_from_u128(7209536115290583917);
+pub const EMPTY_FOG_DATA_HANDLE: Handle<Image> = Handle::weak_from_u128(3185260964730186029);
+
+/// The [`GridMaterial`] extension on top of a [`StandardMaterial`], as spawned on grid meshes
+pub type ExtendedGridMaterial = ExtendedMaterial<StandardMaterial, GridMaterial>;
+
 #[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+#[bind_group_data(GridMaterialKey)]
 pub struct GridMaterial {
     #[uniform(100)]
     pub color: Color,
@@ -31,14 +73,271 @@ pub struct GridMaterial {
     pub subdivisions: UVec2,
     #[uniform(102)]
     pub line_widths: Vec2,
+    #[uniform(103)]
+    pub major_lines: GridLines,
+    #[uniform(104)]
+    pub axis_lines: GridLines,
+    #[uniform(105)]
+    pub fade: GridFade,
+    #[uniform(106)]
+    pub polar: GridPolar,
+
+    /// one texel per cell holding the fill painted into it, written by [`crate::cell_fill::CellFills`]
+    #[texture(107, sample_type = "u_int")]
+    pub cell_data: Handle<Image>,
+    /// cell, counted from the axis lines, stored in the first texel of cell_data
+    #[uniform(108)]
+    pub cell_data_origin: IVec2,
+
+    /// rectangles of cells drawn over the lines, written by [`crate::cell_highlight::CellHighlights`]
+    #[uniform(109)]
+    pub highlights: GridHighlights,
+    /// projection used by [`GridMapping::Triplanar`]
+    #[uniform(110)]
+    pub triplanar: GridTriplanar,
+    /// cells per second the minor and major lines slide across the sheet
+    pub scroll: Vec2,
+    /// cells the lines have slid so far, advanced from scroll every frame by [`GridPlugin`]
+    #[uniform(111)]
+    pub scroll_offset: Vec2,
+    #[uniform(112)]
+    pub ripple: GridRipple,
+    #[uniform(113)]
+    pub reveal: GridReveal,
+    /// procedural surface of the sheet the grid is printed on
+    #[uniform(114)]
+    pub paper: GridPaper,
+    /// straight lines drawn over the major lines, such as the margin of a notebook page
+    #[uniform(115)]
+    pub margin_lines: GridLines,
+    /// binder holes punched out of the sheet, needs an [`AlphaMode::Mask`] base material
+    #[uniform(116)]
+    pub holes: GridHoles,
+    /// color of the axis line running along grid y, axis_lines.color draws the one along x.
+    /// left fully transparent both axes use axis_lines.color
+    #[uniform(117)]
+    pub y_axis_color: Color,
+    /// tint and contour lines following the height of the surface, as drawn on surface plots
+    #[uniform(118)]
+    pub height: GridHeight,
+    /// one texel per cell holding how much of it has been seen, written by
+    /// [`crate::fog_of_war::FogOfWar`]
+    #[texture(119, sample_type = "u_int")]
+    pub fog_data: Handle<Image>,
+    #[uniform(120)]
+    pub fog: GridFog,
+
+    /// shape drawn by the minor and major lines, axis lines are always straight
+    pub pattern: GridPattern,
+
+    /// units used by line_widths and the line_widths of every tier
+    pub line_width_mode: GridLineWidthMode,
+
+    /// how grid cells are laid out over the mesh
+    pub mapping: GridMapping,
+}
+
+impl Default for GridMaterial {
+    fn default() -> Self {
+        Self {
+            color: Color::rgba(0.19, 0.51, 1.0, 1.0),
+            subdivisions: UVec2::new(0, 0),
+            line_widths: Vec2::new(0.01, 0.01),
+            major_lines: GridLines::default(),
+            axis_lines: GridLines::default(),
+            fade: GridFade::default(),
+            polar: GridPolar::default(),
+            cell_data: EMPTY_CELL_DATA_HANDLE,
+            cell_data_origin: IVec2::ZERO,
+            highlights: GridHighlights::default(),
+            triplanar: GridTriplanar::default(),
+            scroll: Vec2::ZERO,
+            scroll_offset: Vec2::ZERO,
+            ripple: GridRipple::default(),
+            reveal: GridReveal::default(),
+            paper: GridPaper::default(),
+            margin_lines: GridLines::default(),
+            holes: GridHoles::default(),
+            y_axis_color: Color::NONE,
+            height: GridHeight::default(),
+            fog_data: EMPTY_FOG_DATA_HANDLE,
+            fog: GridFog::default(),
+            pattern: GridPattern::default(),
+            line_width_mode: GridLineWidthMode::default(),
+            mapping: GridMapping::default(),
+        }
+    }
+}
+
+impl GridMaterial {
+    /// Slides the lines of every scrolling grid material along by its scroll speed
+    pub fn advance_scroll(time: Res<Time>, mut materials: ResMut<Assets<ExtendedGridMaterial>>) {
+        // borrowing every material mutably would mark them all as changed
+        let scrolling: Vec<_> = materials
+            .iter()
+            .filter(|(_, material)| material.extension.scroll != Vec2::ZERO)
+            .map(|(id, _)| id)
+            .collect();
+
+        for id in scrolling {
+            let Some(material) = materials.get_mut(id) else {
+                continue;
+            };
+            let grid = &mut material.extension;
+            let offset = grid.scroll_offset + grid.scroll * time.delta_seconds();
+
+            // the offset is wrapped whenever the lines repeat, so it never grows large enough to
+            // lose precision or jumps back the way a wrapping clock would
+            let period = grid.scroll_period();
+            grid.scroll_offset =
+                Vec2::select(period.cmpgt(Vec2::ZERO), offset.rem_euclid(period), offset);
+        }
+    }
+
+    /// distance in cells after which every scrolling tier of lines looks the same again, 0.0
+    /// along axes where they never line up
+    fn scroll_period(&self) -> Vec2 {
+        // isometric triangles and hexagons repeat every sqrt(3) cells along y
+        let pattern = match self.pattern {
+            GridPattern::Lines | GridPattern::Dots => Vec2::ONE,
+            GridPattern::Isometric | GridPattern::Hex => Vec2::new(1.0, 3.0_f32.sqrt()),
+            GridPattern::Polar => return Vec2::ZERO,
+        };
+
+        let major = &self.major_lines;
+        if major.color.a() == 0.0 {
+            return pattern;
+        }
+        let interval = major.interval.as_vec2();
+        if major.interval.cmpgt(UVec2::ZERO).all() {
+            return pattern * interval;
+        }
+
+        // single major lines never come back, straight ones only line up with the minor lines
+        // when both repeat a whole number of cells apart
+        Vec2::select(
+            interval.cmpgt(Vec2::ZERO) & pattern.cmpeq(Vec2::ONE),
+            interval,
+            Vec2::ZERO,
+        )
+    }
 }
 
 impl MaterialExtension for GridMaterial {
     fn fragment_shader() -> ShaderRef {
         "grid_material.wgsl".into()
     }
+
+    // the standard prepass knows nothing about binder holes
+    fn prepass_fragment_shader() -> ShaderRef {
+        "grid_prepass.wgsl".into()
+    }
+
+    // the deferred gbuffer has to hold the painted base color
+    fn deferred_fragment_shader() -> ShaderRef {
+        "grid_material.wgsl".into()
+    }
+
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayout,
+        key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment
+                .shader_defs
+                .push(key.bind_group_data.pattern.shader_def().into());
+            if key.bind_group_data.line_width_mode == GridLineWidthMode::Pixels {
+                fragment.shader_defs.push("GRID_LINE_WIDTH_PIXELS".into());
+            }
+            if key.bind_group_data.mapping == GridMapping::Triplanar {
+                fragment.shader_defs.push("GRID_TRIPLANAR".into());
+            }
+        }
+
+        Ok(())
+    }
 }
 
+mod uniforms;
+
+pub use uniforms::*;
+
+/// Pipeline key for a [`GridMaterial`], each distinct key compiles its own shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridMaterialKey {
+    pattern: GridPattern,
+    line_width_mode: GridLineWidthMode,
+    mapping: GridMapping,
+}
+
+impl From<&GridMaterial> for GridMaterialKey {
+    fn from(material: &GridMaterial) -> Self {
+        Self {
+            pattern: material.pattern,
+            line_width_mode: material.line_width_mode,
+            mapping: material.mapping,
+        }
+    }
+}
+
+/// The kind of paper drawn by a [`GridMaterial`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GridPattern {
+    /// square graph paper
+    #[default]
+    Lines,
+
+    /// a dot at every line intersection, line_widths.x is the dot diameter
+    Dots,
+
+    /// equilateral triangles with sides one cell long
+    Isometric,
+
+    /// pointy topped hexagons one cell wide
+    Hex,
+
+    /// rings one cell apart around [`GridPolar::origin`] crossed by [`GridPolar::spokes`]
+    Polar,
+}
+
+impl GridPattern {
+    fn shader_def(&self) -> &'static str {
+        match self {
+            GridPattern::Lines => "GRID_PATTERN_LINES",
+            GridPattern::Dots => "GRID_PATTERN_DOTS",
+            GridPattern::Isometric => "GRID_PATTERN_ISOMETRIC",
+            GridPattern::Hex => "GRID_PATTERN_HEX",
+            GridPattern::Polar => "GRID_PATTERN_POLAR",
+        }
+    }
+}
+
+/// Units of the line widths in a [`GridMaterial`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GridLineWidthMode {
+    /// fraction of a grid cell, lines get thicker as the camera zooms in
+    #[default]
+    Cells,
+
+    /// screen pixels, lines stay the same thickness at every zoom level
+    Pixels,
+}
+
+/// Where a [`GridMaterial`] takes its grid coordinates from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GridMapping {
+    /// mesh UVs count cells, as laid out by [`GridPlane`] and [`GridBox`]
+    #[default]
+    Uv,
+
+    /// cells are projected onto the mesh from world space along each axis and blended by the
+    /// surface normal, so any mesh gets paper lined up with its neighbours
+    Triplanar,
+}
+
+#[allow(dead_code)]
 #[derive(Debug, Component)]
 pub struct AsciiModel {
     lines: Vec<String>,
@@ -305,20 +604,26 @@ impl From<GridPlane> for Mesh {
         let num_vertices = (z_vertex_count * x_vertex_count) as usize;
         let num_indices = ((z_vertex_count - 1) * (x_vertex_count - 1) * 6) as usize;
         let up = Vec3::Y.to_array();
+        // uvs count up across the plane rather than mirroring on every other vertex,
+        // mirrored uvs would flip the tangent frame from one quad to the next.
+        // u runs along +x and v along +z, w flips the bitangent so it follows -v
+        let tangent = [1.0, 0.0, 0.0, 1.0];
 
         let mut positions: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
         let mut normals: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
         let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(num_vertices);
+        let mut tangents: Vec<[f32; 4]> = Vec::with_capacity(num_vertices);
         let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
 
         for z in 0..z_vertex_count {
             for x in 0..x_vertex_count {
                 let tx = x as f32 / (x_vertex_count - 1) as f32;
                 let tz = z as f32 / (z_vertex_count - 1) as f32;
-                let ux = (x % 2) as f32;
-                let uz = (z % 2) as f32;
+                let ux = x as f32;
+                let uz = z as f32;
                 positions.push([(-0.5 + tx) * plane.size, 0.0, (-0.5 + tz) * plane.size]);
                 normals.push(up);
+                tangents.push(tangent);
                 uvs.push([ux, uz]);
             }
         }
@@ -340,237 +645,447 @@ impl From<GridPlane> for Mesh {
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
             .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
             .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
     }
 }
 
-pub struct GridBox {
-    pub size: Vec3,
-    pub subdivisions: UVec3,
+/// A flat rectangle of grid cells whose uvs count cells from the grid origin rather than from
+/// the corner of the mesh, so neighbouring chunks continue each other's lines
+///
+/// The mesh starts at its local origin and extends along +x and +z.
+#[derive(Debug, Clone, Copy)]
+pub struct GridChunk {
+    /// first cell covered by the chunk
+    pub min: IVec2,
+
+    /// number of cells covered along each axis
+    pub cells: UVec2,
+
+    /// world size of a single cell
+    pub cell_size: f32,
+
+    /// number of quads the mesh is split into along each axis, a chunk covering more cells
+    /// with the same number of quads is a coarser mesh of the same surface
+    pub quads: UVec2,
 }
 
-impl From<GridBox> for Mesh {
-    fn from(value: GridBox) -> Self {
-        let x_vertex_count = value.subdivisions.x + 2;
-        let y_vertex_count = value.subdivisions.y + 2;
-        let z_vertex_count = value.subdivisions.z + 2;
-
-        let num_vertices = (((z_vertex_count * x_vertex_count)
-            + (z_vertex_count * y_vertex_count)
-            + (x_vertex_count * y_vertex_count))
-            * 2) as usize;
-        let num_indices = ((((z_vertex_count - 1) * (x_vertex_count - 1))
-            + ((z_vertex_count - 1) * (y_vertex_count - 1))
-            + ((x_vertex_count - 1) * (y_vertex_count - 1)))
-            * 6
-            * 2) as usize;
-        let x_up = Vec3::X.to_array();
-        let x_down = (Vec3::X * -1.0).to_array();
-        let y_up = Vec3::Y.to_array();
-        let y_down = (Vec3::Y * -1.0).to_array();
-        let z_up = Vec3::Z.to_array();
-        let z_down = (Vec3::Z * -1.0).to_array();
+impl From<GridChunk> for Mesh {
+    fn from(value: GridChunk) -> Self {
+        let size = value.cells.as_vec2() * value.cell_size;
+        let uv_min = value.min.as_vec2();
+        let quads = value.quads.max(UVec2::ONE);
+        let vertex_count = quads + 1;
+        let num_vertices = (vertex_count.x * vertex_count.y) as usize;
+        let num_indices = (quads.x * quads.y * 6) as usize;
+        let up = Vec3::Y.to_array();
+        // u runs along +x and v along +z like GridPlane
+        let tangent = [1.0, 0.0, 0.0, 1.0];
 
         let mut positions: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
         let mut normals: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
         let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(num_vertices);
+        let mut tangents: Vec<[f32; 4]> = Vec::with_capacity(num_vertices);
         let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
-        let mut index_offset: u32 = 0;
 
-        // Front Mesh
-        for z in 0..z_vertex_count {
-            for x in 0..x_vertex_count {
-                let tx = x as f32 / (x_vertex_count - 1) as f32;
-                let ty = 1.0 as f32;
-                let tz = z as f32 / (z_vertex_count - 1) as f32;
-                let ux = (x % 2) as f32;
-                let uz = (z % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(y_up);
-                uvs.push([ux, uz]);
+        for z in 0..vertex_count.y {
+            for x in 0..vertex_count.x {
+                let t = UVec2::new(x, z).as_vec2() / quads.as_vec2();
+                let position = t * size;
+                let uv = uv_min + t * value.cells.as_vec2();
+                positions.push([position.x, 0.0, position.y]);
+                normals.push(up);
+                tangents.push(tangent);
+                uvs.push(uv.to_array());
             }
         }
 
-        // Front Indices
-        for z in 0..z_vertex_count - 1 {
-            for x in 0..x_vertex_count - 1 {
-                let quad = z * x_vertex_count + x;
-                indices.push(quad + x_vertex_count + 1);
+        for z in 0..quads.y {
+            for x in 0..quads.x {
+                let quad = z * vertex_count.x + x;
+                indices.push(quad + vertex_count.x + 1);
                 indices.push(quad + 1);
-                indices.push(quad + x_vertex_count);
+                indices.push(quad + vertex_count.x);
                 indices.push(quad);
-                indices.push(quad + x_vertex_count);
+                indices.push(quad + vertex_count.x);
                 indices.push(quad + 1);
             }
         }
 
-        // Back Mesh
-        index_offset = positions.len() as u32;
-        for z in 0..z_vertex_count {
-            for x in 0..x_vertex_count {
-                let tx = x as f32 / (x_vertex_count - 1) as f32;
-                let ty = 0.0 as f32;
-                let tz = z as f32 / (z_vertex_count - 1) as f32;
-                let ux = (x % 2) as f32;
-                let uz = (z % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(y_down);
-                uvs.push([ux, uz]);
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_indices(Some(Indices::U32(indices)))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    }
+}
+
+/// Outline of the hexes in a [`HexGrid`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HexShape {
+    /// every hex at most radius steps from hex (0, 0)
+    Hexagon { radius: u32 },
+
+    /// columns along x and rows along z starting at hex (0, 0), every second row (or column
+    /// for flat topped hexes) is staggered so the board stays rectangular
+    Rectangle { columns: u32, rows: u32 },
+}
+
+/// A flat board of hexagons on the xz plane, with hex (0, 0) centered on the origin
+///
+/// The uvs are laid out for [`GridPattern::Hex`] with one uv cell per hex width,
+/// so the material's hex lines follow the edges of the mesh for either orientation.
+#[derive(Debug, Clone, Copy, Component)]
+pub struct HexGrid {
+    pub orientation: HexOrientation,
+
+    /// distance from the center of a hex to its corners
+    pub size: f32,
+
+    pub shape: HexShape,
+}
+
+impl HexGrid {
+    /// every hex on the board
+    pub fn hexes(&self) -> Vec<HexCoord> {
+        match self.shape {
+            HexShape::Hexagon { radius } => HexCoord::ZERO.spiral(radius),
+            HexShape::Rectangle { columns, rows } => {
+                let mut hexes = Vec::with_capacity((columns * rows) as usize);
+                for row in 0..rows as i32 {
+                    for column in 0..columns as i32 {
+                        // every second row or column shifts back half a hex, which axial
+                        // coordinates count as a whole step
+                        hexes.push(match self.orientation {
+                            HexOrientation::Pointy => {
+                                HexCoord::new(column - row.div_euclid(2), row)
+                            }
+                            HexOrientation::Flat => {
+                                HexCoord::new(column, row - column.div_euclid(2))
+                            }
+                        });
+                    }
+                }
+                hexes
             }
         }
+    }
 
-        // Back Indices
-        for z in 0..z_vertex_count - 1 {
-            for x in 0..x_vertex_count - 1 {
-                let quad = index_offset + z * x_vertex_count + x;
-                indices.push(quad + 1);
-                indices.push(quad + x_vertex_count + 1);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad);
-                indices.push(quad + 1);
+    pub fn contains(&self, hex: HexCoord) -> bool {
+        match self.shape {
+            HexShape::Hexagon { radius } => hex.distance(HexCoord::ZERO) <= radius,
+            HexShape::Rectangle { columns, rows } => {
+                let (column, row) = match self.orientation {
+                    HexOrientation::Pointy => (hex.q + hex.r.div_euclid(2), hex.r),
+                    HexOrientation::Flat => (hex.q, hex.r + hex.q.div_euclid(2)),
+                };
+                (0..rows as i32).contains(&row) && (0..columns as i32).contains(&column)
             }
         }
+    }
 
-        // Top Mesh
-        index_offset = positions.len() as u32;
-        for y in 0..y_vertex_count {
-            for x in 0..x_vertex_count {
-                let tx = x as f32 / (x_vertex_count - 1) as f32;
-                let ty = y as f32 / (y_vertex_count - 1) as f32;
-                let tz = 1.0 as f32;
-                let ux = (x % 2) as f32;
-                let uy = (y % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(z_up);
-                uvs.push([ux, uy]);
-            }
+    /// local position of the center of a hex
+    pub fn hex_center(&self, hex: HexCoord) -> Vec3 {
+        let center = self.orientation.hex_to_local(hex, self.size);
+        Vec3::new(center.x, 0.0, center.y)
+    }
+
+    /// hex under a local position, which may be off the board
+    pub fn local_to_hex(&self, local: Vec3) -> HexCoord {
+        self.orientation
+            .local_to_hex(Vec2::new(local.x, local.z), self.size)
+    }
+
+    /// uv of a local position, hex (0, 0) is centered on the uv origin
+    fn uv(&self, local: Vec2) -> [f32; 2] {
+        // the hex pattern is pointy topped, flat topped boards have their uvs turned
+        // sideways to match
+        let uv = local / (3.0_f32.sqrt() * self.size);
+        match self.orientation {
+            HexOrientation::Pointy => [uv.x, uv.y],
+            HexOrientation::Flat => [uv.y, uv.x],
         }
+    }
+}
 
-        // Top Indices
-        for y in 0..y_vertex_count - 1 {
-            for x in 0..x_vertex_count - 1 {
-                let quad = index_offset + y * x_vertex_count + x;
-                indices.push(quad + 1);
-                indices.push(quad + x_vertex_count + 1);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad);
-                indices.push(quad + 1);
+impl From<HexGrid> for Mesh {
+    fn from(value: HexGrid) -> Self {
+        let hexes = value.hexes();
+        let corners = value.orientation.corners(value.size);
+
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(hexes.len() * 7);
+        let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(hexes.len() * 7);
+        let mut indices: Vec<u32> = Vec::with_capacity(hexes.len() * 18);
+
+        for hex in hexes {
+            let center = value.orientation.hex_to_local(hex, value.size);
+            let center_index = positions.len() as u32;
+            positions.push([center.x, 0.0, center.y]);
+            uvs.push(value.uv(center));
+            for corner in corners {
+                let corner = center + corner;
+                positions.push([corner.x, 0.0, corner.y]);
+                uvs.push(value.uv(corner));
             }
-        }
 
-        // Bottom Mesh
-        index_offset = positions.len() as u32;
-        for y in 0..y_vertex_count {
-            for x in 0..x_vertex_count {
-                let tx = x as f32 / (x_vertex_count - 1) as f32;
-                let ty = y as f32 / (y_vertex_count - 1) as f32;
-                let tz = 0.0 as f32;
-                let ux = (x % 2) as f32;
-                let uy = (y % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(z_down);
-                uvs.push([ux, uy]);
+            // a fan around the center, the corners already go counter clockwise from above
+            for i in 0..6 {
+                indices.push(center_index);
+                indices.push(center_index + 1 + i);
+                indices.push(center_index + 1 + (i + 1) % 6);
             }
         }
 
-        // Bottom Indices
-        for y in 0..y_vertex_count - 1 {
-            for x in 0..x_vertex_count - 1 {
-                let quad = index_offset + y * x_vertex_count + x;
-                indices.push(quad + x_vertex_count + 1);
-                indices.push(quad + 1);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad + x_vertex_count);
-                indices.push(quad + 1);
-                indices.push(quad);
-            }
+        let normals = vec![Vec3::Y.to_array(); positions.len()];
+        // tangents point along +u, w is picked so that the bitangent
+        // (w * normal x tangent) points along -v
+        let tangent = match value.orientation {
+            HexOrientation::Pointy => [1.0, 0.0, 0.0, 1.0],
+            HexOrientation::Flat => [0.0, 0.0, 1.0, -1.0],
+        };
+        let tangents = vec![tangent; positions.len()];
+
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_indices(Some(Indices::U32(indices)))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Component)]
+pub struct GridBox {
+    pub size: Vec3,
+    pub subdivisions: UVec3,
+
+    /// radius of the rounded edges and corners, 0 leaves them sharp
+    pub bevel_radius: f32,
+
+    /// number of segments around each rounded edge, rounded up to an even number
+    pub bevel_segments: u32,
+}
+
+impl GridBox {
+    pub fn new(size: Vec3, subdivisions: UVec3) -> Self {
+        Self {
+            size,
+            subdivisions,
+            bevel_radius: 0.0,
+            bevel_segments: 0,
         }
+    }
 
-        // Right Mesh
-        index_offset = positions.len() as u32;
-        for y in 0..y_vertex_count {
-            for z in 0..z_vertex_count {
-                let tx = 1.0 as f32;
-                let ty = y as f32 / (y_vertex_count - 1) as f32;
-                let tz = z as f32 / (z_vertex_count - 1) as f32;
-                let uz = (z % 2) as f32;
-                let uy = (y % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(x_up);
-                uvs.push([uz, uy]);
-            }
+    pub fn with_bevel(mut self, radius: f32, segments: u32) -> Self {
+        self.bevel_radius = radius;
+        self.bevel_segments = segments;
+        self
+    }
+
+    /// bevel radius that fits the box, 0 when the edges are sharp
+    fn radius(&self) -> f32 {
+        if self.bevel_segments == 0 {
+            return 0.0;
+        }
+        self.bevel_radius.clamp(0.0, self.size.min_element() * 0.5)
+    }
+
+    /// length of a face measured over the surface, from the middle of one rounded edge to the
+    /// middle of the opposite one, the face's uvs are spread evenly over it
+    fn unrolled_size(&self) -> Vec3 {
+        let radius = self.radius();
+        self.size - Vec3::splat(2.0 * radius) + Vec3::splat(FRAC_PI_2 * radius)
+    }
+
+    /// Layout of the cells drawn by `material` on the top face of the box
+    ///
+    /// The grid origin is placed at the material's axis lines, so cell coordinates
+    /// count outwards from the axes.
+    pub fn layout(&self, material: &GridMaterial, transform: &GlobalTransform) -> GridLayout {
+        let cells = UVec2::new(
+            (self.subdivisions.x + 1) * (material.subdivisions.x + 1),
+            (self.subdivisions.z + 1) * (material.subdivisions.y + 1),
+        );
+        let (scale, rotation, _) = transform.to_scale_rotation_translation();
+        let unrolled_size = self.unrolled_size();
+        let cell_size = Vec2::new(unrolled_size.x, unrolled_size.z) * Vec2::new(scale.x, scale.z)
+            / cells.as_vec2();
+
+        // the flat part of the top face continues the cells of the rounded edges
+        let corner = transform.transform_point(Vec3::new(
+            -0.5 * unrolled_size.x,
+            0.5 * self.size.y,
+            -0.5 * unrolled_size.z,
+        ));
+        let offset = material.axis_lines.offset;
+        let origin =
+            corner + rotation * Vec3::new(offset.x * cell_size.x, 0.0, offset.y * cell_size.y);
+
+        let first_cell = (-offset).floor().as_ivec2();
+        let last_cell = first_cell + cells.as_ivec2() - IVec2::ONE;
+
+        GridLayout {
+            origin,
+            cell_size,
+            rotation,
+            bounds: IRect::from_corners(first_cell, last_cell),
         }
+    }
 
-        // Right Indices
-        for y in 0..y_vertex_count - 1 {
-            for z in 0..z_vertex_count - 1 {
-                let quad = index_offset + y * x_vertex_count + z;
-                indices.push(quad + z_vertex_count + 1);
-                indices.push(quad + 1);
-                indices.push(quad + z_vertex_count);
-                indices.push(quad + z_vertex_count);
-                indices.push(quad + 1);
-                indices.push(quad);
+    /// Vertex positions across a face along one axis, paired with their uv
+    ///
+    /// Each face covers half of the rounded edges on either side, so faces meet halfway round
+    /// the bevel. The uvs count one cell per subdivision and are spread evenly over the
+    /// surface, so they hit whole cells where the faces meet and the lines wrap over the edges.
+    fn face_steps(&self, axis: usize) -> Vec<(f32, f32)> {
+        let radius = self.radius();
+        let half_segments = self.bevel_segments.div_ceil(2);
+        let flat = self.size[axis] * 0.5 - radius;
+        let arc = radius * FRAC_PI_4;
+        let cells = (self.subdivisions.to_array()[axis] + 1) as f32;
+        let to_uv = |distance: f32| distance / self.unrolled_size()[axis].max(1e-6) * cells;
+
+        let mut steps = Vec::new();
+        // positions past the flat part are pushed out by tan(angle), which lands them on the
+        // angle once they are rounded onto the bevel
+        if radius > 0.0 {
+            for i in 0..half_segments {
+                let angle = FRAC_PI_4 * (half_segments - i) as f32 / half_segments as f32;
+                steps.push((
+                    -flat - radius * angle.tan(),
+                    to_uv(radius * (FRAC_PI_4 - angle)),
+                ));
+            }
+        }
+        for i in 0..cells as u32 + 1 {
+            let t = i as f32 / cells;
+            steps.push((-flat + 2.0 * flat * t, to_uv(arc + 2.0 * flat * t)));
+        }
+        if radius > 0.0 {
+            for i in 1..=half_segments {
+                let angle = FRAC_PI_4 * i as f32 / half_segments as f32;
+                steps.push((
+                    flat + radius * angle.tan(),
+                    to_uv(arc + 2.0 * flat + radius * angle),
+                ));
             }
         }
+        steps
+    }
 
-        // Left Mesh
-        index_offset = positions.len() as u32;
-        for y in 0..y_vertex_count {
-            for z in 0..z_vertex_count {
-                let tx = 0.0 as f32;
-                let ty = y as f32 / (y_vertex_count - 1) as f32;
-                let tz = z as f32 / (z_vertex_count - 1) as f32;
-                let uz = (z % 2) as f32;
-                let uy = (y % 2) as f32;
-                positions.push([
-                    (-0.5 + tx) * value.size.x,
-                    (-0.5 + ty) * value.size.y,
-                    (-0.5 + tz) * value.size.z,
-                ]);
-                normals.push(x_down);
-                uvs.push([uz, uy]);
+    /// Adds the face facing `normal_sign` along `normal_axis` to the mesh, with u running
+    /// along `u_axis` and v along `v_axis`
+    #[allow(clippy::too_many_arguments)]
+    fn push_face(
+        &self,
+        u_axis: usize,
+        v_axis: usize,
+        normal_axis: usize,
+        normal_sign: f32,
+        positions: &mut Vec<[f32; 3]>,
+        normals: &mut Vec<[f32; 3]>,
+        uvs: &mut Vec<[f32; 2]>,
+        tangents: &mut Vec<[f32; 4]>,
+        indices: &mut Vec<u32>,
+    ) {
+        let radius = self.radius();
+        let half_size = self.size * 0.5;
+        let inner_size = half_size - Vec3::splat(radius);
+
+        let mut face_normal = Vec3::ZERO;
+        face_normal[normal_axis] = normal_sign;
+        let mut u_direction = Vec3::ZERO;
+        u_direction[u_axis] = 1.0;
+        let mut v_direction = Vec3::ZERO;
+        v_direction[v_axis] = 1.0;
+        // tangents point along +u, w is picked so that the bitangent
+        // (w * normal x tangent) points along -v
+        let w = -face_normal.cross(u_direction).dot(v_direction).signum();
+
+        let u_steps = self.face_steps(u_axis);
+        let v_steps = self.face_steps(v_axis);
+        let index_offset = positions.len() as u32;
+
+        for (v_position, v_uv) in v_steps.iter() {
+            for (u_position, u_uv) in u_steps.iter() {
+                let mut flat_position = face_normal * half_size;
+                flat_position[u_axis] = *u_position;
+                flat_position[v_axis] = *v_position;
+
+                // round everything outside the inner box onto the bevel
+                let inner = flat_position.clamp(-inner_size, inner_size);
+                let normal = (flat_position - inner).normalize_or_zero();
+                let (position, normal) = if radius > 0.0 && normal != Vec3::ZERO {
+                    (inner + normal * radius, normal)
+                } else {
+                    (flat_position, face_normal)
+                };
+                let tangent = (u_direction - normal * normal.dot(u_direction)).normalize_or_zero();
+
+                positions.push(position.to_array());
+                normals.push(normal.to_array());
+                uvs.push([*u_uv, *v_uv]);
+                tangents.push([tangent.x, tangent.y, tangent.z, w]);
             }
         }
 
-        // Left Indices
-        for y in 0..y_vertex_count - 1 {
-            for z in 0..z_vertex_count - 1 {
-                let quad = index_offset + y * x_vertex_count + z;
-                indices.push(quad + 1);
-                indices.push(quad + z_vertex_count + 1);
-                indices.push(quad + z_vertex_count);
-                indices.push(quad + 1);
-                indices.push(quad + z_vertex_count);
-                indices.push(quad);
+        // wind the triangles so they face out of the box
+        let outward = u_direction.cross(v_direction).dot(face_normal) > 0.0;
+        let u_vertex_count = u_steps.len() as u32;
+        for v in 0..v_steps.len() as u32 - 1 {
+            for u in 0..u_vertex_count - 1 {
+                let quad = index_offset + v * u_vertex_count + u;
+                let next_u = quad + 1;
+                let next_v = quad + u_vertex_count;
+                let next_uv = next_v + 1;
+                if outward {
+                    indices.extend([next_u, next_uv, next_v, next_v, quad, next_u]);
+                } else {
+                    indices.extend([next_uv, next_u, next_v, quad, next_v, next_u]);
+                }
             }
         }
+    }
+}
+
+impl From<GridBox> for Mesh {
+    fn from(value: GridBox) -> Self {
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut normals: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut tangents: Vec<[f32; 4]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+
+        // uvs count up across each face so the tangent frame stays continuous
+        for (u_axis, v_axis, normal_axis, normal_sign) in [
+            // Front and Back
+            (0, 2, 1, 1.0),
+            (0, 2, 1, -1.0),
+            // Top and Bottom
+            (0, 1, 2, 1.0),
+            (0, 1, 2, -1.0),
+            // Right and Left
+            (2, 1, 0, 1.0),
+            (2, 1, 0, -1.0),
+        ] {
+            value.push_face(
+                u_axis,
+                v_axis,
+                normal_axis,
+                normal_sign,
+                &mut positions,
+                &mut normals,
+                &mut uvs,
+                &mut tangents,
+                &mut indices,
+            );
+        }
 
         Mesh::new(PrimitiveTopology::TriangleList)
             .with_indices(Some(Indices::U32(indices)))
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
             .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
             .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
     }
 }
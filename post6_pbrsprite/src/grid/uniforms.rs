@@ -0,0 +1,348 @@
+use bevy::prelude::*;
+
+use crate::fog_of_war::FogState;
+
+// ShaderType's derive emits a size check for every field that is never called, so the structs
+// are declared in a module of their own where allowing it covers nothing but the derives and
+// the constructors and builders below are linted as usual
+#[allow(dead_code)]
+mod shader_types;
+
+pub use shader_types::*;
+
+impl Default for GridTriplanar {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            cell_size: 1.0,
+            sharpness: 8.0,
+        }
+    }
+}
+
+impl GridTriplanar {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_origin(mut self, origin: Vec3) -> Self {
+        self.origin = origin;
+        self
+    }
+}
+
+impl Default for GridRipple {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            start_time: 0.0,
+            color: Color::NONE,
+            speed: 0.0,
+            width: 0.0,
+            duration: 0.0,
+        }
+    }
+}
+
+impl GridRipple {
+    pub fn new(color: Color, speed: f32, width: f32, duration: f32) -> Self {
+        Self {
+            color,
+            speed,
+            width,
+            duration,
+            ..Default::default()
+        }
+    }
+
+    /// sends a new ring out from origin, replacing any ring still spreading
+    pub fn start(&mut self, origin: Vec3, time: &Time) {
+        self.origin = origin;
+        self.start_time = time.elapsed_seconds_wrapped();
+    }
+}
+
+impl Default for GridReveal {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            start_time: 0.0,
+            direction: Vec3::ZERO,
+            speed: 0.0,
+            softness: 0.0,
+        }
+    }
+}
+
+impl GridReveal {
+    /// lines appear in a growing circle around origin
+    pub fn radial(origin: Vec3, speed: f32) -> Self {
+        Self {
+            origin,
+            speed,
+            ..Default::default()
+        }
+    }
+
+    /// lines appear behind a straight edge starting at origin and moving along direction
+    pub fn sweep(origin: Vec3, direction: Vec3, speed: f32) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize_or_zero(),
+            speed,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_softness(mut self, softness: f32) -> Self {
+        self.softness = softness;
+        self
+    }
+
+    /// restarts the reveal from the origin
+    pub fn start(&mut self, time: &Time) {
+        self.start_time = time.elapsed_seconds_wrapped();
+    }
+}
+
+impl Default for GridHoles {
+    fn default() -> Self {
+        Self {
+            first: Vec2::ZERO,
+            spacing: Vec2::ZERO,
+            count: 0,
+            radius: 0.0,
+        }
+    }
+}
+
+impl GridHoles {
+    pub fn new(first: Vec2, spacing: Vec2, count: u32, radius: f32) -> Self {
+        Self {
+            first,
+            spacing,
+            count,
+            radius,
+        }
+    }
+}
+
+impl Default for GridPaper {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            fiber_strength: 0.0,
+            fiber_scale: 1.0,
+            wrinkle_height: 0.0,
+            wrinkle_scale: 1.0,
+            stain_color: Color::NONE,
+            stain_density: 0.0,
+            stain_scale: 1.0,
+            edge_color: Color::NONE,
+            edge_width: 0.0,
+            sheet_size: Vec2::ZERO,
+        }
+    }
+}
+
+impl GridPaper {
+    /// blank paper, enable layers with the with_ methods
+    pub fn new(seed: u32) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_fibers(mut self, strength: f32, scale: f32) -> Self {
+        self.fiber_strength = strength;
+        self.fiber_scale = scale;
+        self
+    }
+
+    pub fn with_wrinkles(mut self, height: f32, scale: f32) -> Self {
+        self.wrinkle_height = height;
+        self.wrinkle_scale = scale;
+        self
+    }
+
+    pub fn with_stains(mut self, color: Color, density: f32, scale: f32) -> Self {
+        self.stain_color = color;
+        self.stain_density = density;
+        self.stain_scale = scale;
+        self
+    }
+}
+
+impl Default for GridHeight {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            up: Vec3::Y,
+            low_color: Color::NONE,
+            high_color: Color::NONE,
+            low: 0.0,
+            high: 0.0,
+            contour_color: Color::NONE,
+            contour_interval: 0.0,
+            contour_width: 0.0,
+        }
+    }
+}
+
+impl GridHeight {
+    /// heights measured from origin along up, neither tinted nor contoured yet
+    pub fn new(origin: Vec3, up: Vec3) -> Self {
+        Self {
+            origin,
+            up,
+            ..Default::default()
+        }
+    }
+
+    /// heights along the local y axis of an entity, in its local units
+    pub fn from_transform(transform: &Transform) -> Self {
+        Self::new(
+            transform.translation,
+            transform.rotation * Vec3::Y * transform.scale.y,
+        )
+    }
+
+    pub fn with_colors(mut self, low_color: Color, high_color: Color, low: f32, high: f32) -> Self {
+        self.low_color = low_color;
+        self.high_color = high_color;
+        self.low = low;
+        self.high = high;
+        self
+    }
+
+    pub fn with_contours(mut self, color: Color, interval: f32, width: f32) -> Self {
+        self.contour_color = color;
+        self.contour_interval = interval;
+        self.contour_width = width;
+        self
+    }
+}
+
+impl Default for GridFog {
+    fn default() -> Self {
+        Self {
+            origin: IVec2::ZERO,
+            outside: FogState::Visible.shader_id(),
+            explored_fade: 0.6,
+        }
+    }
+}
+
+impl Default for GridPolar {
+    fn default() -> Self {
+        Self {
+            origin: Vec2::ZERO,
+            spokes: 24,
+        }
+    }
+}
+
+impl Default for GridLines {
+    fn default() -> Self {
+        Self {
+            color: Color::NONE,
+            line_widths: Vec2::ZERO,
+            interval: UVec2::ZERO,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl GridLines {
+    /// lines repeating every `interval` cells
+    pub fn every(interval: UVec2, color: Color, line_widths: Vec2) -> Self {
+        Self {
+            color,
+            line_widths,
+            interval,
+            offset: Vec2::ZERO,
+        }
+    }
+
+    /// a single line on each axis passing through `offset`
+    pub fn axis(color: Color, line_widths: Vec2) -> Self {
+        Self {
+            color,
+            line_widths,
+            interval: UVec2::ZERO,
+            offset: Vec2::ZERO,
+        }
+    }
+}
+
+impl Default for GridFade {
+    fn default() -> Self {
+        Self {
+            min_spacing_pixels: 0.0,
+            spacing_fade_pixels: 0.0,
+            distance_start: 0.0,
+            distance_end: 0.0,
+        }
+    }
+}
+
+impl GridFade {
+    pub fn with_spacing(mut self, min_spacing_pixels: f32, spacing_fade_pixels: f32) -> Self {
+        self.min_spacing_pixels = min_spacing_pixels;
+        self.spacing_fade_pixels = spacing_fade_pixels;
+        self
+    }
+
+    pub fn with_distance(mut self, distance_start: f32, distance_end: f32) -> Self {
+        self.distance_start = distance_start;
+        self.distance_end = distance_end;
+        self
+    }
+}
+
+impl Default for GridHighlights {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            rects: [GridHighlightRect::default(); MAX_GRID_HIGHLIGHTS],
+        }
+    }
+}
+
+impl GridHighlights {
+    /// adds a rect, returns false once every slot is taken
+    pub fn push(&mut self, rect: GridHighlightRect) -> bool {
+        let Some(slot) = self.rects.get_mut(self.count as usize) else {
+            return false;
+        };
+
+        *slot = rect;
+        self.count += 1;
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.count = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GridHighlightRect> {
+        self.rects[..self.count as usize].iter()
+    }
+}
+
+impl Default for GridHighlightRect {
+    fn default() -> Self {
+        Self {
+            min: IVec2::ZERO,
+            max: IVec2::ZERO,
+            color: Color::NONE,
+            style: 0,
+            outline_width: 0.0,
+            group: 0,
+        }
+    }
+}
@@ -0,0 +1,247 @@
+use bevy::{prelude::*, render::render_resource::ShaderType};
+use serde::{Deserialize, Serialize};
+
+/// World space projection used by [`crate::grid::GridMapping::Triplanar`]
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridTriplanar {
+    /// world position of a grid line intersection
+    pub origin: Vec3,
+
+    /// world size of a single grid cell
+    pub cell_size: f32,
+
+    /// how quickly the projections blend into each other where the surface turns, higher
+    /// values give sharper seams
+    pub sharpness: f32,
+}
+
+/// A ring spreading out from a world position that lights up the lines it passes over
+///
+/// Times are measured by the shader's global time, which is
+/// [`Time::elapsed_seconds_wrapped`], so rings started just before it wraps are cut short.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridRipple {
+    /// world position the ring starts from
+    pub origin: Vec3,
+
+    /// time the ring was started
+    pub start_time: f32,
+
+    pub color: Color,
+
+    /// world units the ring grows per second
+    pub speed: f32,
+
+    /// world width of the ring
+    pub width: f32,
+
+    /// seconds until the ring has faded out, 0 disables the ripple
+    pub duration: f32,
+}
+
+/// Draws the lines on progressively, hiding them beyond a front moving away from the origin
+///
+/// Like [`GridRipple`] times are measured in [`Time::elapsed_seconds_wrapped`].
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridReveal {
+    /// world position the front starts from
+    pub origin: Vec3,
+
+    /// time the front left the origin
+    pub start_time: f32,
+
+    /// when zero the front is a circle growing around the origin, otherwise it is a straight
+    /// edge sweeping along this direction
+    pub direction: Vec3,
+
+    /// world units the front moves per second, 0 disables the reveal and shows every line
+    pub speed: f32,
+
+    /// world width over which lines fade in behind the front
+    pub softness: f32,
+}
+
+/// A row of round holes punched through the sheet, measured in grid cells from the uv origin
+#[derive(Debug, Clone, Copy, ShaderType, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridHoles {
+    /// center of the first hole
+    pub first: Vec2,
+
+    /// offset from the center of one hole to the next
+    pub spacing: Vec2,
+
+    /// number of holes, 0 disables the holes
+    pub count: u32,
+
+    pub radius: f32,
+}
+
+/// Procedural paper underneath the grid lines, built from optional layers
+///
+/// Every layer is generated from the seed, so two sheets with the same seed look the same.
+/// Sizes are measured in grid cells from the uv origin.
+#[derive(Debug, Clone, Copy, ShaderType, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridPaper {
+    pub seed: u32,
+
+    /// how much the fibers brighten and darken the paper, 0 disables fibers
+    pub fiber_strength: f32,
+
+    /// size of a single fiber
+    pub fiber_scale: f32,
+
+    /// world height of the creases, 0 disables wrinkles
+    pub wrinkle_height: f32,
+
+    /// distance between large creases
+    pub wrinkle_scale: f32,
+
+    /// color multiplied into stained paper, alpha controls how dark the stains get
+    pub stain_color: Color,
+
+    /// chance between 0 and 1 that a stain site holds a coffee ring, 0 disables stains
+    pub stain_density: f32,
+
+    /// distance between stain sites, rings are up to about this wide
+    pub stain_scale: f32,
+
+    /// color multiplied into the paper towards the edges, alpha controls how yellow it gets
+    pub edge_color: Color,
+
+    /// how far in from the edges the paper yellows, 0 disables yellowing
+    pub edge_width: f32,
+
+    /// size of the sheet, edges are at 0 and at this size
+    pub sheet_size: Vec2,
+}
+
+/// Tints the paper by its height and draws contour lines of equal height over it
+///
+/// Heights are measured in world space from `origin` along `up`, so they follow the surface
+/// whatever its mesh, like the heights of a [`crate::plot::SurfacePlot`].
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridHeight {
+    /// world position at height 0
+    pub origin: Vec3,
+
+    /// world offset from height 0 to height 1
+    pub up: Vec3,
+
+    /// tint at and below low, alpha controls how much of the paper it covers
+    pub low_color: Color,
+
+    /// tint at and above high
+    pub high_color: Color,
+
+    /// heights the tint blends between, equal heights disable the tint
+    pub low: f32,
+    pub high: f32,
+
+    pub contour_color: Color,
+
+    /// height between two contour lines, 0 disables contours
+    pub contour_interval: f32,
+
+    /// width of the contour lines in units of height, or in pixels when the material uses
+    /// [`crate::grid::GridLineWidthMode::Pixels`]
+    pub contour_width: f32,
+}
+
+/// How the [`crate::grid::GridMaterial`] draws cells hidden by the fog of war
+///
+/// Unexplored cells show blank paper and explored ones keep a faded copy of their fills and
+/// lines. The fog is blended between neighbouring cells, so its edge is soft.
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridFog {
+    /// cell, counted from the axis lines, stored in the first texel of fog_data
+    pub origin: IVec2,
+
+    /// state of the cells outside of fog_data, as a [`crate::fog_of_war::FogState`] id.
+    /// every cell is visible by default, which leaves the fog off
+    pub outside: u32,
+
+    /// how far explored cells fade towards blank paper, from 0 to 1
+    pub explored_fade: f32,
+}
+
+/// Layout of the [`crate::grid::GridPattern::Polar`] pattern
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridPolar {
+    /// center of the rings measured in grid cells from the uv origin
+    pub origin: Vec2,
+
+    /// number of lines radiating out from the origin
+    pub spokes: u32,
+}
+
+/// A tier of grid lines drawn on top of the minor lines of a [`crate::grid::GridMaterial`]
+#[derive(Debug, Clone, Copy, ShaderType, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridLines {
+    pub color: Color,
+
+    /// width of the lines as a fraction of a single grid cell, or in pixels when the
+    /// material uses [`crate::grid::GridLineWidthMode::Pixels`]
+    pub line_widths: Vec2,
+
+    /// number of grid cells between two lines, 0 draws a single straight line at the offset.
+    /// repeating tiers follow the material's [`crate::grid::GridPattern`], for
+    /// [`crate::grid::GridPattern::Polar`] x counts rings and y counts spokes
+    pub interval: UVec2,
+
+    /// position of a line measured in grid cells from the uv origin
+    pub offset: Vec2,
+}
+
+/// Controls how grid lines fade out before they turn into noise
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridFade {
+    /// spacing in screen pixels at which a tier of repeating lines has fully faded out,
+    /// denser tiers reach it first so minor lines fade before major ones
+    pub min_spacing_pixels: f32,
+
+    /// how many pixels above min_spacing_pixels a tier starts to fade, 0 disables spacing fade
+    pub spacing_fade_pixels: f32,
+
+    /// view distance at which the lines start to fade into the base color
+    pub distance_start: f32,
+
+    /// view distance at which the lines are fully faded, must be above distance_start to
+    /// enable distance fade
+    pub distance_end: f32,
+}
+
+/// Most rectangles a single [`crate::grid::GridMaterial`] can highlight at once
+pub const MAX_GRID_HIGHLIGHTS: usize = 64;
+
+/// Highlighted rectangles of cells drawn by a [`crate::grid::GridMaterial`]
+#[derive(Debug, Clone, Copy, ShaderType)]
+pub struct GridHighlights {
+    /// number of rects in use, the rest are ignored
+    pub count: u32,
+    pub rects: [GridHighlightRect; MAX_GRID_HIGHLIGHTS],
+}
+
+/// A single highlighted rectangle of cells as laid out for the grid shader
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType)]
+pub struct GridHighlightRect {
+    /// first cell, counted from the axis lines like a [`crate::grid_coord::GridCoord`]
+    pub min: IVec2,
+
+    /// last cell, inclusive
+    pub max: IVec2,
+
+    pub color: Color,
+
+    /// 0 outline, 1 fill, 2 pulse
+    pub style: u32,
+
+    /// outline width as a fraction of a single grid cell
+    pub outline_width: f32,
+
+    /// rects sharing a group other than 0 are outlined as a single shape, without the edges
+    /// where they touch
+    pub group: u32,
+}
@@ -0,0 +1,425 @@
+use std::ops::{Add, Sub};
+
+use bevy::{prelude::*, transform::TransformSystem, window::PrimaryWindow};
+
+use crate::{
+    grid::{CameraTarget, ExtendedGridMaterial, GridBox},
+    paper_ground::PaperGround,
+};
+
+pub struct GridCoordPlugin;
+
+impl Plugin for GridCoordPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GridLayout>()
+            .init_resource::<HoveredCell>()
+            .add_systems(
+                Update,
+                (GridLayout::update, HoveredCell::update)
+                    .chain()
+                    .after(CameraTarget::update),
+            )
+            // snap once everything has moved for the frame, before the transforms propagate
+            .add_systems(
+                PostUpdate,
+                SnapToGrid::update.before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Integer position of a single cell on the grid
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GridCoord {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl GridCoord {
+    pub const ZERO: GridCoord = GridCoord { x: 0, y: 0 };
+
+    pub fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    pub fn as_ivec2(&self) -> IVec2 {
+        IVec2::new(self.x, self.y)
+    }
+}
+
+impl From<IVec2> for GridCoord {
+    fn from(value: IVec2) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+impl From<GridCoord> for IVec2 {
+    fn from(value: GridCoord) -> Self {
+        value.as_ivec2()
+    }
+}
+
+impl Add for GridCoord {
+    type Output = GridCoord;
+
+    fn add(self, rhs: GridCoord) -> GridCoord {
+        GridCoord::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl Sub for GridCoord {
+    type Output = GridCoord;
+
+    fn sub(self, rhs: GridCoord) -> GridCoord {
+        GridCoord::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+/// Describes where the cells of the spawned grid sit in the world
+///
+/// Grid x runs along the local x axis of the grid and grid y along its local z axis,
+/// the grid's local y axis is the surface normal.
+#[derive(Resource, Debug, Clone, Copy, PartialEq)]
+pub struct GridLayout {
+    /// world position of the corner shared by cells (-1, -1) and (0, 0)
+    pub origin: Vec3,
+
+    /// world size of a single cell
+    pub cell_size: Vec2,
+
+    /// rotation from grid space into world space
+    pub rotation: Quat,
+
+    /// first and last cell on the grid, inclusive
+    pub bounds: IRect,
+}
+
+impl Default for GridLayout {
+    fn default() -> Self {
+        Self {
+            origin: Vec3::ZERO,
+            cell_size: Vec2::ONE,
+            rotation: Quat::IDENTITY,
+            bounds: IRect::new(i32::MIN, i32::MIN, i32::MAX, i32::MAX),
+        }
+    }
+}
+
+impl GridLayout {
+    /// Derives the layout from the spawned [`GridBox`], or the [`PaperGround`] when there is
+    /// no box
+    pub fn update(
+        grid_query: Query<(&GridBox, &GlobalTransform, &Handle<ExtendedGridMaterial>)>,
+        ground_query: Query<(
+            &PaperGround,
+            &GlobalTransform,
+            &Handle<ExtendedGridMaterial>,
+        )>,
+        materials: Res<Assets<ExtendedGridMaterial>>,
+        mut layout: ResMut<GridLayout>,
+    ) {
+        let new_layout = if let Ok((grid_box, transform, material)) = grid_query.get_single() {
+            let Some(material) = materials.get(material) else {
+                return;
+            };
+            grid_box.layout(&material.extension, transform)
+        } else if let Ok((ground, transform, material)) = ground_query.get_single() {
+            let Some(material) = materials.get(material) else {
+                return;
+            };
+            ground.layout(&material.extension, transform)
+        } else {
+            return;
+        };
+
+        if *layout != new_layout {
+            *layout = new_layout;
+        }
+    }
+
+    /// surface normal of the grid in world space
+    pub fn normal(&self) -> Vec3 {
+        self.rotation * Vec3::Y
+    }
+
+    /// continuous grid position of a world position, measured in cells from the origin
+    pub fn world_to_grid(&self, world: Vec3) -> Vec2 {
+        let local = self.rotation.inverse() * (world - self.origin);
+        Vec2::new(local.x, local.z) / self.cell_size
+    }
+
+    /// world position of a continuous grid position, on the grid surface
+    pub fn grid_to_world(&self, grid: Vec2) -> Vec3 {
+        let local = grid * self.cell_size;
+        self.origin + self.rotation * Vec3::new(local.x, 0.0, local.y)
+    }
+
+    /// cell containing a world position
+    pub fn world_to_cell(&self, world: Vec3) -> GridCoord {
+        self.world_to_grid(world).floor().as_ivec2().into()
+    }
+
+    /// world position of the center of a cell
+    pub fn cell_center(&self, cell: GridCoord) -> Vec3 {
+        self.grid_to_world(cell.as_ivec2().as_vec2() + Vec2::splat(0.5))
+    }
+
+    /// world position of the corner of a cell closest to the origin
+    pub fn cell_corner(&self, cell: GridCoord) -> Vec3 {
+        self.grid_to_world(cell.as_ivec2().as_vec2())
+    }
+
+    pub fn contains(&self, cell: GridCoord) -> bool {
+        self.bounds.contains(cell.as_ivec2())
+    }
+
+    /// point where a ray hits the grid surface, if it does
+    pub fn intersect_ray(&self, ray: Ray) -> Option<Vec3> {
+        ray.intersect_plane(self.origin, self.normal())
+            .map(|distance| ray.get_point(distance))
+    }
+}
+
+/// The cell currently under the mouse cursor
+#[derive(Resource, Debug, Default, Clone, Copy, PartialEq)]
+pub struct HoveredCell {
+    /// cell under the cursor, None when the cursor is off the grid or outside the window
+    pub cell: Option<GridCoord>,
+
+    /// world position where the cursor ray meets the grid surface
+    pub world_position: Option<Vec3>,
+}
+
+impl HoveredCell {
+    pub fn update(
+        window_query: Query<&Window, With<PrimaryWindow>>,
+        camera_query: Query<(&Camera, &GlobalTransform), With<CameraTarget>>,
+        layout: Res<GridLayout>,
+        mut hovered: ResMut<HoveredCell>,
+    ) {
+        let world_position = window_query
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .zip(camera_query.get_single().ok())
+            .and_then(|(cursor, (camera, camera_transform))| {
+                camera.viewport_to_world(camera_transform, cursor)
+            })
+            .and_then(|ray| layout.intersect_ray(ray));
+
+        let cell = world_position
+            .map(|position| layout.world_to_cell(position))
+            .filter(|cell| layout.contains(*cell));
+
+        let new_hovered = HoveredCell {
+            cell,
+            world_position: cell.and(world_position),
+        };
+        if *hovered != new_hovered {
+            *hovered = new_hovered;
+        }
+    }
+}
+
+/// Where a [`SnapToGrid`] entity may come to rest
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SnapPoint {
+    /// the centers of the cells
+    #[default]
+    Centers,
+
+    /// the corners shared by neighbouring cells
+    Intersections,
+}
+
+/// Keeps the translation of an entity on the cells of the [`GridLayout`]
+///
+/// The position across the grid is snapped once per frame after the entity has moved, its
+/// height above the grid is left alone. The translation is taken as a world position, so the
+/// entity shouldn't have a parent.
+///
+/// Only the translation is snapped, the unsnapped position is kept as the target. Whatever
+/// moves the translation between two snaps moves the target by as much, so movement slower
+/// than a snapping step per frame still adds up instead of being snapped back every frame.
+#[derive(Component, Debug, Clone, Copy, PartialEq)]
+pub struct SnapToGrid {
+    pub point: SnapPoint,
+
+    /// number of snapping steps along each side of a cell, 1 snaps to whole cells
+    pub resolution: u32,
+
+    /// world position the entity would be at without snapping, None until the first snap
+    target: Option<Vec3>,
+
+    /// translation written by the last snap
+    snapped: Vec3,
+}
+
+impl Default for SnapToGrid {
+    fn default() -> Self {
+        Self {
+            point: SnapPoint::Centers,
+            resolution: 1,
+            target: None,
+            snapped: Vec3::ZERO,
+        }
+    }
+}
+
+impl SnapToGrid {
+    /// snaps to the centers of the cells
+    pub fn centers() -> Self {
+        Self::default()
+    }
+
+    /// snaps to the corners of the cells
+    pub fn intersections() -> Self {
+        Self {
+            point: SnapPoint::Intersections,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// nearest position to a continuous grid position this snaps to, both in cells
+    pub fn snap(&self, grid: Vec2) -> Vec2 {
+        let steps = self.resolution.max(1) as f32;
+        let scaled = grid * steps;
+        let snapped = match self.point {
+            SnapPoint::Centers => scaled.floor() + Vec2::splat(0.5),
+            SnapPoint::Intersections => scaled.round(),
+        };
+        snapped / steps
+    }
+
+    /// world position the entity would be at without snapping, None before the first snap
+    pub fn target(&self) -> Option<Vec3> {
+        self.target
+    }
+
+    pub fn update(
+        layout: Res<GridLayout>,
+        mut snap_query: Query<(&mut SnapToGrid, &mut Transform)>,
+    ) {
+        let normal = layout.normal();
+        for (mut snap, mut transform) in snap_query.iter_mut() {
+            let target = match snap.target {
+                Some(target) => target + (transform.translation - snap.snapped),
+                None => transform.translation,
+            };
+            let height = (target - layout.origin).dot(normal);
+            let grid = layout.world_to_grid(target);
+            let snapped = layout.grid_to_world(snap.snap(grid)) + normal * height;
+
+            snap.set_if_neq(SnapToGrid {
+                target: Some(target),
+                snapped,
+                ..*snap
+            });
+            // only write when it moved, so resting entities don't show up as changed
+            if transform.translation != snapped {
+                transform.translation = snapped;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> GridLayout {
+        GridLayout {
+            origin: Vec3::new(10.0, 2.0, -5.0),
+            cell_size: Vec2::new(30.0, 12.5),
+            rotation: Quat::from_rotation_y(0.3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn cells_round_trip_through_world_positions() {
+        let layout = layout();
+        for y in -4..=3 {
+            for x in -4..=3 {
+                let cell = GridCoord::new(x, y);
+                assert_eq!(layout.world_to_cell(layout.cell_center(cell)), cell);
+
+                // just inside the corners on either side, where an off by one floor would show
+                let first = cell.as_ivec2().as_vec2();
+                for inside in [Vec2::splat(0.01), Vec2::new(0.99, 0.01), Vec2::splat(0.99)] {
+                    let world = layout.grid_to_world(first + inside);
+                    assert_eq!(layout.world_to_cell(world), cell, "{inside} in {cell:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn grid_positions_round_trip_off_the_surface() {
+        let layout = layout();
+        for grid in [Vec2::new(-2.25, 0.5), Vec2::new(3.75, -7.1), Vec2::ZERO] {
+            let world = layout.grid_to_world(grid);
+            assert!(layout.world_to_grid(world).abs_diff_eq(grid, 1e-4));
+
+            // the height above the surface doesn't change the cell
+            let above = world + layout.normal() * 40.0;
+            assert!(layout.world_to_grid(above).abs_diff_eq(grid, 1e-4));
+        }
+        assert_eq!(layout.world_to_cell(layout.origin), GridCoord::ZERO);
+        assert_eq!(
+            layout.world_to_cell(layout.grid_to_world(Vec2::splat(-0.01))),
+            GridCoord::new(-1, -1)
+        );
+    }
+
+    #[test]
+    fn snapping_rounds_towards_the_nearest_point() {
+        let centers = SnapToGrid::centers();
+        assert_eq!(centers.snap(Vec2::new(-0.2, -1.7)), Vec2::new(-0.5, -1.5));
+        assert_eq!(centers.snap(Vec2::new(2.9, 0.0)), Vec2::new(2.5, 0.5));
+
+        let intersections = SnapToGrid::intersections();
+        assert_eq!(
+            intersections.snap(Vec2::new(-0.4, -1.6)),
+            Vec2::new(0.0, -2.0)
+        );
+
+        let halves = SnapToGrid::centers().with_resolution(2);
+        assert_eq!(halves.snap(Vec2::new(-0.2, 0.6)), Vec2::new(-0.25, 0.75));
+    }
+
+    #[test]
+    fn slow_movement_adds_up_while_snapped() {
+        let mut world = World::new();
+        let layout = GridLayout {
+            cell_size: Vec2::splat(30.0),
+            ..Default::default()
+        };
+        world.insert_resource(layout);
+        let start = layout.cell_center(GridCoord::new(-2, 1)) + Vec3::Y * 5.0;
+        let entity = world
+            .spawn((SnapToGrid::centers(), Transform::from_translation(start)))
+            .id();
+
+        let mut update = IntoSystem::into_system(SnapToGrid::update);
+        update.initialize(&mut world);
+        update.run((), &mut world);
+        // a tenth of a cell per frame along -x, never enough to leave the cell in one frame
+        for _ in 0..10 {
+            let mut transform = world.get_mut::<Transform>(entity).unwrap();
+            transform.translation.x -= 3.0;
+            update.run((), &mut world);
+        }
+
+        let translation = world.get::<Transform>(entity).unwrap().translation;
+        assert_eq!(layout.world_to_cell(translation), GridCoord::new(-3, 1));
+        assert!(translation.abs_diff_eq(
+            layout.cell_center(GridCoord::new(-3, 1)) + Vec3::Y * 5.0,
+            1e-3
+        ));
+    }
+}
@@ -0,0 +1,357 @@
+use std::{cmp::Reverse, collections::BinaryHeap};
+
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+use crate::{grid_coord::GridCoord, plot::Plot};
+
+/// cost of a step to a side neighbour, a diagonal step costs about √2 times as much
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+/// Which cells of the grid can be walked through, for finding paths around obstacles
+///
+/// Searches never leave the bounds, so they end even when a goal can't be reached.
+#[derive(Resource, Debug, Clone)]
+pub struct NavGrid {
+    /// first and last cell that can be walked on, inclusive
+    pub bounds: IRect,
+
+    /// whether paths may step diagonally, they never cut the corner of a blocked cell
+    pub diagonal: bool,
+
+    blocked: HashSet<GridCoord>,
+}
+
+impl NavGrid {
+    pub fn new(bounds: IRect) -> Self {
+        Self {
+            bounds,
+            diagonal: false,
+            blocked: HashSet::new(),
+        }
+    }
+
+    pub fn with_diagonal(mut self, diagonal: bool) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+
+    pub fn is_walkable(&self, cell: GridCoord) -> bool {
+        self.bounds.contains(cell.as_ivec2()) && !self.blocked.contains(&cell)
+    }
+
+    pub fn is_blocked(&self, cell: GridCoord) -> bool {
+        self.blocked.contains(&cell)
+    }
+
+    pub fn block(&mut self, cell: GridCoord) {
+        self.blocked.insert(cell);
+    }
+
+    pub fn unblock(&mut self, cell: GridCoord) {
+        self.blocked.remove(&cell);
+    }
+
+    pub fn blocked(&self) -> impl Iterator<Item = &GridCoord> {
+        self.blocked.iter()
+    }
+
+    /// walkable neighbours of a cell with the cost of stepping onto them
+    fn neighbors(&self, cell: GridCoord) -> Vec<(GridCoord, u32)> {
+        let mut neighbors = Vec::with_capacity(8);
+        for offset in [IVec2::X, IVec2::Y, IVec2::NEG_X, IVec2::NEG_Y] {
+            let neighbor = cell + offset.into();
+            if self.is_walkable(neighbor) {
+                neighbors.push((neighbor, STRAIGHT_COST));
+            }
+        }
+
+        if self.diagonal {
+            for offset in [
+                IVec2::ONE,
+                IVec2::new(-1, 1),
+                IVec2::NEG_ONE,
+                IVec2::new(1, -1),
+            ] {
+                let neighbor = cell + offset.into();
+                // both cells beside the step have to be free to squeeze through
+                let corners = [
+                    cell + IVec2::new(offset.x, 0).into(),
+                    cell + IVec2::new(0, offset.y).into(),
+                ];
+                if self.is_walkable(neighbor) && corners.iter().all(|c| self.is_walkable(*c)) {
+                    neighbors.push((neighbor, DIAGONAL_COST));
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// lowest possible cost from a cell to the goal
+    fn heuristic(&self, cell: GridCoord, goal: GridCoord) -> u32 {
+        let delta = (goal - cell).as_ivec2().abs().as_uvec2();
+        if self.diagonal {
+            let (low, high) = (delta.min_element(), delta.max_element());
+            DIAGONAL_COST * low + STRAIGHT_COST * (high - low)
+        } else {
+            STRAIGHT_COST * (delta.x + delta.y)
+        }
+    }
+
+    /// Shortest walkable path from start to goal with A*, both ends included
+    pub fn find_path(&self, start: GridCoord, goal: GridCoord) -> Option<Vec<GridCoord>> {
+        if !self.is_walkable(start) || !self.is_walkable(goal) {
+            return None;
+        }
+
+        let mut open = BinaryHeap::new();
+        let mut costs: HashMap<GridCoord, u32> = HashMap::new();
+        let mut came_from: HashMap<GridCoord, GridCoord> = HashMap::new();
+        costs.insert(start, 0);
+        open.push(Reverse((self.heuristic(start, goal), 0, start.x, start.y)));
+
+        while let Some(Reverse((_, cost, x, y))) = open.pop() {
+            let cell = GridCoord::new(x, y);
+            if cell == goal {
+                let mut path = vec![goal];
+                while let Some(previous) = came_from.get(path.last().unwrap()) {
+                    path.push(*previous);
+                }
+                path.reverse();
+                return Some(path);
+            }
+            // a cheaper way here was already expanded
+            if costs.get(&cell).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            for (neighbor, step_cost) in self.neighbors(cell) {
+                let neighbor_cost = cost + step_cost;
+                if costs
+                    .get(&neighbor)
+                    .is_some_and(|best| neighbor_cost >= *best)
+                {
+                    continue;
+                }
+
+                costs.insert(neighbor, neighbor_cost);
+                came_from.insert(neighbor, cell);
+                let estimate = neighbor_cost + self.heuristic(neighbor, goal);
+                open.push(Reverse((estimate, neighbor_cost, neighbor.x, neighbor.y)));
+            }
+        }
+        None
+    }
+
+    /// Flood fills out from start, returning every cell that can be reached within range
+    /// steps along with the number of steps it takes. diagonal steps count as about 1.4
+    pub fn reachable(&self, start: GridCoord, range: f32) -> HashMap<GridCoord, f32> {
+        let mut costs: HashMap<GridCoord, u32> = HashMap::new();
+        if !self.is_walkable(start) {
+            return HashMap::new();
+        }
+
+        let max_cost = (range.max(0.0) * STRAIGHT_COST as f32).round() as u32;
+        let mut open = BinaryHeap::new();
+        costs.insert(start, 0);
+        open.push(Reverse((0, start.x, start.y)));
+
+        while let Some(Reverse((cost, x, y))) = open.pop() {
+            let cell = GridCoord::new(x, y);
+            if costs.get(&cell).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            for (neighbor, step_cost) in self.neighbors(cell) {
+                let neighbor_cost = cost + step_cost;
+                if neighbor_cost > max_cost
+                    || costs
+                        .get(&neighbor)
+                        .is_some_and(|best| neighbor_cost >= *best)
+                {
+                    continue;
+                }
+
+                costs.insert(neighbor, neighbor_cost);
+                open.push(Reverse((neighbor_cost, neighbor.x, neighbor.y)));
+            }
+        }
+
+        costs
+            .into_iter()
+            .map(|(cell, cost)| (cell, cost as f32 / STRAIGHT_COST as f32))
+            .collect()
+    }
+
+    /// Whether a straight line between the centers of two cells passes no blocked cell,
+    /// the cells at either end may be blocked themselves so walls can be seen
+    ///
+    /// Every cell the line touches is checked. Where it runs exactly through a corner it is
+    /// stopped by a wall on either side, like a diagonal step of a path.
+    pub fn line_of_sight(&self, from: GridCoord, to: GridCoord) -> bool {
+        let delta = (to - from).as_ivec2();
+        let (steps, sign) = (delta.abs(), delta.signum());
+        let mut cell = from.as_ivec2();
+        let mut taken = IVec2::ZERO;
+        while taken.x < steps.x || taken.y < steps.y {
+            // compares where the line crosses the next column and the next row of cells
+            let decision = (1 + 2 * taken.x) * steps.y - (1 + 2 * taken.y) * steps.x;
+            if decision == 0 {
+                let sides = [cell + IVec2::new(sign.x, 0), cell + IVec2::new(0, sign.y)];
+                if sides.iter().any(|side| self.is_blocked((*side).into())) {
+                    return false;
+                }
+                cell += sign;
+                taken += IVec2::ONE;
+            } else if decision < 0 {
+                cell.x += sign.x;
+                taken.x += 1;
+            } else {
+                cell.y += sign.y;
+                taken.y += 1;
+            }
+
+            if cell != to.as_ivec2() && self.is_blocked(cell.into()) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// A dashed ink line through the centers of the cells of a path
+    pub fn path_plot(path: &[GridCoord]) -> Plot {
+        let points = path
+            .iter()
+            .map(|cell| cell.as_ivec2().as_vec2() + Vec2::splat(0.5))
+            .collect();
+        Plot::polyline(points)
+            .with_thickness(0.12)
+            .with_dash(0.4, 0.25)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nav_grid(blocked: &[(i32, i32)]) -> NavGrid {
+        let mut nav_grid = NavGrid::new(IRect::new(-5, -5, 5, 5));
+        for (x, y) in blocked {
+            nav_grid.block(GridCoord::new(*x, *y));
+        }
+        nav_grid
+    }
+
+    #[test]
+    fn walled_off_goal_is_unreachable() {
+        // a closed ring of walls around (3, 3)
+        let mut walls = Vec::new();
+        for x in 2..=4 {
+            for y in 2..=4 {
+                if (x, y) != (3, 3) {
+                    walls.push((x, y));
+                }
+            }
+        }
+        let nav_grid = nav_grid(&walls).with_diagonal(true);
+        assert_eq!(
+            nav_grid.find_path(GridCoord::ZERO, GridCoord::new(3, 3)),
+            None
+        );
+        assert_eq!(
+            nav_grid.find_path(GridCoord::ZERO, GridCoord::new(9, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn blocked_start_or_goal_has_no_path() {
+        let nav_grid = nav_grid(&[(0, 0), (2, 0)]);
+        assert_eq!(
+            nav_grid.find_path(GridCoord::ZERO, GridCoord::new(1, 1)),
+            None
+        );
+        assert_eq!(
+            nav_grid.find_path(GridCoord::new(1, 1), GridCoord::new(2, 0)),
+            None
+        );
+        assert!(nav_grid.reachable(GridCoord::ZERO, 5.0).is_empty());
+    }
+
+    #[test]
+    fn diagonal_steps_dont_cut_corners() {
+        let nav_grid = nav_grid(&[(1, 0)]).with_diagonal(true);
+        let path = nav_grid
+            .find_path(GridCoord::ZERO, GridCoord::new(1, 1))
+            .unwrap();
+        assert_eq!(
+            path,
+            [GridCoord::ZERO, GridCoord::new(0, 1), GridCoord::new(1, 1)]
+        );
+
+        // with both sides blocked the diagonal is shut
+        let mut nav_grid = nav_grid;
+        nav_grid.block(GridCoord::new(0, 1));
+        let path = nav_grid
+            .find_path(GridCoord::ZERO, GridCoord::new(1, 1))
+            .unwrap();
+        assert!(path.len() > 3);
+        for step in path.windows(2) {
+            let delta = (step[1] - step[0]).as_ivec2();
+            if delta.x != 0 && delta.y != 0 {
+                assert!(nav_grid.is_walkable(step[0] + GridCoord::new(delta.x, 0)));
+                assert!(nav_grid.is_walkable(step[0] + GridCoord::new(0, delta.y)));
+            }
+        }
+    }
+
+    #[test]
+    fn range_is_rounded_to_step_costs() {
+        let nav_grid = nav_grid(&[]).with_diagonal(true);
+        let diagonal = GridCoord::new(1, 1);
+
+        let reachable = nav_grid.reachable(GridCoord::ZERO, 1.4);
+        assert_eq!(reachable[&GridCoord::ZERO], 0.0);
+        assert_eq!(reachable[&GridCoord::new(1, 0)], 1.0);
+        assert_eq!(reachable[&diagonal], 1.4);
+        assert_eq!(reachable.len(), 9);
+
+        // 1.35 rounds up to a diagonal step, 1.3 falls short of one
+        assert!(nav_grid
+            .reachable(GridCoord::ZERO, 1.35)
+            .contains_key(&diagonal));
+        assert!(!nav_grid
+            .reachable(GridCoord::ZERO, 1.3)
+            .contains_key(&diagonal));
+        assert_eq!(nav_grid.reachable(GridCoord::ZERO, -1.0).len(), 1);
+    }
+
+    #[test]
+    fn diagonal_wall_joints_block_line_of_sight() {
+        let joint = nav_grid(&[(1, 0), (0, 1)]);
+        assert!(!joint.line_of_sight(GridCoord::ZERO, GridCoord::new(2, 2)));
+        assert!(!joint.line_of_sight(GridCoord::new(3, 3), GridCoord::ZERO));
+
+        // like a path, a line past the corner of a single wall is stopped as well
+        let corner = nav_grid(&[(1, 0)]);
+        assert!(!corner.line_of_sight(GridCoord::ZERO, GridCoord::new(2, 2)));
+        assert!(corner.line_of_sight(GridCoord::new(0, 1), GridCoord::new(2, 3)));
+    }
+
+    #[test]
+    fn walls_block_line_of_sight() {
+        let nav_grid = nav_grid(&[(2, 0), (0, 3)]);
+        assert!(!nav_grid.line_of_sight(GridCoord::ZERO, GridCoord::new(4, 0)));
+        assert!(!nav_grid.line_of_sight(GridCoord::new(0, 5), GridCoord::ZERO));
+        assert!(nav_grid.line_of_sight(GridCoord::ZERO, GridCoord::new(4, 2)));
+        // the line to (4, 1) grazes the wall at (2, 0) on its way past
+        assert!(!nav_grid.line_of_sight(GridCoord::ZERO, GridCoord::new(4, 1)));
+
+        // walls themselves can be seen
+        assert!(nav_grid.line_of_sight(GridCoord::ZERO, GridCoord::new(2, 0)));
+        assert!(nav_grid.line_of_sight(GridCoord::new(2, 0), GridCoord::ZERO));
+    }
+}
@@ -0,0 +1,268 @@
+use bevy::{
+    asset::LoadState,
+    prelude::*,
+    render::{
+        mesh::Indices,
+        render_resource::{PrimitiveTopology, TextureFormat},
+    },
+};
+use thiserror::Error;
+
+pub struct GridTerrainPlugin;
+
+impl Plugin for GridTerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, PendingGridTerrain::build);
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum HeightmapError {
+    #[error("heightmaps can't be read from {0:?} images")]
+    UnsupportedFormat(TextureFormat),
+}
+
+/// Grid of heights in the range [0.0, 1.0]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heightmap {
+    size: UVec2,
+
+    /// heights row by row, the first row lies along the -z edge of a terrain
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    pub fn new(size: UVec2, heights: Vec<f32>) -> Self {
+        assert_eq!(
+            heights.len(),
+            (size.x * size.y) as usize,
+            "heightmap size doesn't match its number of heights"
+        );
+        Self { size, heights }
+    }
+
+    /// Reads the heights from the first channel of a grayscale or color image
+    pub fn from_image(image: &Image) -> Result<Self, HeightmapError> {
+        let format = image.texture_descriptor.format;
+        let data = &image.data;
+        let heights: Vec<f32> = match format {
+            TextureFormat::R8Unorm => data.iter().map(|value| *value as f32 / 255.0).collect(),
+            // grayscale pngs are expanded to rgba when loaded
+            TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => data
+                .chunks_exact(4)
+                .map(|texel| texel[0] as f32 / 255.0)
+                .collect(),
+            TextureFormat::R16Uint | TextureFormat::R16Unorm => data
+                .chunks_exact(2)
+                .map(|texel| u16::from_le_bytes([texel[0], texel[1]]) as f32 / 65535.0)
+                .collect(),
+            TextureFormat::Rg16Uint => data
+                .chunks_exact(4)
+                .map(|texel| u16::from_le_bytes([texel[0], texel[1]]) as f32 / 65535.0)
+                .collect(),
+            TextureFormat::Rgba16Unorm => data
+                .chunks_exact(8)
+                .map(|texel| u16::from_le_bytes([texel[0], texel[1]]) as f32 / 65535.0)
+                .collect(),
+            TextureFormat::R32Float => data
+                .chunks_exact(4)
+                .map(|texel| f32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]))
+                .collect(),
+            TextureFormat::Rgba32Float => data
+                .chunks_exact(16)
+                .map(|texel| f32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]]))
+                .collect(),
+            format => return Err(HeightmapError::UnsupportedFormat(format)),
+        };
+
+        Ok(Self::new(image.size(), heights))
+    }
+
+    pub fn size(&self) -> UVec2 {
+        self.size
+    }
+
+    fn get(&self, texel: UVec2) -> f32 {
+        let texel = texel.min(self.size - UVec2::ONE);
+        self.heights[(texel.y * self.size.x + texel.x) as usize]
+    }
+
+    /// Bilinearly filtered height, uv (0, 0) is the center of the first texel
+    /// and (1, 1) the center of the last
+    pub fn sample(&self, uv: Vec2) -> f32 {
+        if self.heights.is_empty() {
+            return 0.0;
+        }
+
+        let texel = uv.clamp(Vec2::ZERO, Vec2::ONE) * (self.size - UVec2::ONE).as_vec2();
+        let first = texel.floor();
+        let t = texel - first;
+        let first = first.as_uvec2();
+
+        let top = Vec2::new(self.get(first), self.get(first + UVec2::X));
+        let bottom = Vec2::new(self.get(first + UVec2::Y), self.get(first + UVec2::ONE));
+        let row = top.lerp(bottom, t.y);
+        row.x + (row.y - row.x) * t.x
+    }
+}
+
+/// A grid draped over a heightmap
+///
+/// The terrain is centered on x and z and rises from 0 up to `size.y` where the heightmap is 1.
+/// The uvs count the distance over the surface along each row and column of vertices, one uv
+/// cell for every quad width, so the grid lines run over the hills without the cells stretching
+/// on the slopes.
+#[derive(Debug, Clone)]
+pub struct GridTerrain {
+    pub size: Vec3,
+
+    /// number of additional vertex rows along x and z
+    pub subdivisions: UVec2,
+
+    pub heightmap: Heightmap,
+}
+
+impl GridTerrain {
+    /// height of the heightmap above a local position on the xz plane,
+    /// the mesh follows it exactly at its vertices
+    pub fn height_at(&self, position: Vec2) -> f32 {
+        let uv = position / Vec2::new(self.size.x, self.size.z) + Vec2::splat(0.5);
+        self.heightmap.sample(uv) * self.size.y
+    }
+
+    fn vertex_position(&self, vertex: IVec2) -> Vec3 {
+        let vertex_count = self.subdivisions + UVec2::new(2, 2);
+        let vertex = vertex.clamp(IVec2::ZERO, vertex_count.as_ivec2() - IVec2::ONE);
+        let uv = vertex.as_vec2() / (vertex_count - UVec2::ONE).as_vec2();
+        let position = (uv - Vec2::splat(0.5)) * Vec2::new(self.size.x, self.size.z);
+        Vec3::new(
+            position.x,
+            self.heightmap.sample(uv) * self.size.y,
+            position.y,
+        )
+    }
+}
+
+impl From<GridTerrain> for Mesh {
+    fn from(value: GridTerrain) -> Self {
+        let x_vertex_count = value.subdivisions.x + 2;
+        let z_vertex_count = value.subdivisions.y + 2;
+        let num_vertices = (x_vertex_count * z_vertex_count) as usize;
+        let num_indices = ((x_vertex_count - 1) * (z_vertex_count - 1) * 6) as usize;
+
+        let mut positions: Vec<Vec3> = Vec::with_capacity(num_vertices);
+        let mut normals: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
+        let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(num_vertices);
+        let mut tangents: Vec<[f32; 4]> = Vec::with_capacity(num_vertices);
+        let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
+
+        // world size of a quad seen from above, a cell of surface distance
+        let quad_size = Vec2::new(value.size.x, value.size.z)
+            / Vec2::new((x_vertex_count - 1) as f32, (z_vertex_count - 1) as f32);
+
+        for z in 0..z_vertex_count as i32 {
+            for x in 0..x_vertex_count as i32 {
+                let vertex = IVec2::new(x, z);
+                let position = value.vertex_position(vertex);
+                // central differences over the neighbouring vertices, one sided along the edges
+                let along_x = value.vertex_position(vertex + IVec2::X)
+                    - value.vertex_position(vertex - IVec2::X);
+                let along_z = value.vertex_position(vertex + IVec2::Y)
+                    - value.vertex_position(vertex - IVec2::Y);
+                let normal = along_z.cross(along_x).normalize_or_zero();
+                // u runs along x over the surface, like GridPlane
+                let tangent = along_x.normalize_or_zero();
+
+                // u continues from the previous vertex in the row and v from the previous one
+                // in the column, by the distance between them over the surface
+                let index = positions.len();
+                let u = if x == 0 {
+                    0.0
+                } else {
+                    let [u, _] = uvs[index - 1];
+                    u + position.distance(positions[index - 1]) / quad_size.x
+                };
+                let v = if z == 0 {
+                    0.0
+                } else {
+                    let above = index - x_vertex_count as usize;
+                    let [_, v] = uvs[above];
+                    v + position.distance(positions[above]) / quad_size.y
+                };
+
+                positions.push(position);
+                normals.push(normal.to_array());
+                uvs.push([u, v]);
+                tangents.push([tangent.x, tangent.y, tangent.z, 1.0]);
+            }
+        }
+        let positions: Vec<[f32; 3]> = positions
+            .iter()
+            .map(|position| position.to_array())
+            .collect();
+
+        for z in 0..z_vertex_count - 1 {
+            for x in 0..x_vertex_count - 1 {
+                let quad = z * x_vertex_count + x;
+                indices.push(quad + x_vertex_count + 1);
+                indices.push(quad + 1);
+                indices.push(quad + x_vertex_count);
+                indices.push(quad);
+                indices.push(quad + x_vertex_count);
+                indices.push(quad + 1);
+            }
+        }
+
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_indices(Some(Indices::U32(indices)))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    }
+}
+
+/// Builds the mesh of a [`GridTerrain`] once its heightmap image has loaded
+///
+/// The entity gets the terrain's `Handle<Mesh>` and the component is removed. If the heightmap
+/// fails to load the component is removed without a mesh.
+#[derive(Component, Debug, Clone)]
+pub struct PendingGridTerrain {
+    pub heightmap: Handle<Image>,
+    pub size: Vec3,
+    pub subdivisions: UVec2,
+}
+
+impl PendingGridTerrain {
+    pub fn build(
+        mut commands: Commands,
+        pending_query: Query<(Entity, &PendingGridTerrain)>,
+        asset_server: Res<AssetServer>,
+        images: Res<Assets<Image>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        for (entity, pending) in pending_query.iter() {
+            let Some(image) = images.get(&pending.heightmap) else {
+                if asset_server.get_load_state(&pending.heightmap) == Some(LoadState::Failed) {
+                    warn!("could not build grid terrain: its heightmap failed to load");
+                    commands.entity(entity).remove::<PendingGridTerrain>();
+                }
+                continue;
+            };
+
+            let mut entity = commands.entity(entity);
+            entity.remove::<PendingGridTerrain>();
+            match Heightmap::from_image(image) {
+                Ok(heightmap) => {
+                    let terrain = GridTerrain {
+                        size: pending.size,
+                        subdivisions: pending.subdivisions,
+                        heightmap,
+                    };
+                    entity.insert(meshes.add(terrain.into()));
+                }
+                Err(err) => error!("could not build grid terrain: {err}"),
+            }
+        }
+    }
+}
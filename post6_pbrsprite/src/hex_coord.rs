@@ -0,0 +1,280 @@
+use std::ops::{Add, Mul, Sub};
+
+use bevy::prelude::*;
+
+/// Which way the hexagons of a hex grid point
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HexOrientation {
+    /// a corner points along +z and -z, rows of hexes run along x
+    #[default]
+    Pointy,
+
+    /// a corner points along +x and -x, columns of hexes run along z
+    Flat,
+}
+
+const SQRT_3: f32 = 1.732_050_8;
+
+impl HexOrientation {
+    /// center of a hex on the grid's xz plane, size is the distance from a center to a corner
+    pub fn hex_to_local(&self, hex: HexCoord, size: f32) -> Vec2 {
+        let (q, r) = (hex.q as f32, hex.r as f32);
+        let local = match self {
+            HexOrientation::Pointy => Vec2::new(SQRT_3 * q + SQRT_3 * 0.5 * r, 1.5 * r),
+            HexOrientation::Flat => Vec2::new(1.5 * q, SQRT_3 * 0.5 * q + SQRT_3 * r),
+        };
+        local * size
+    }
+
+    /// hex containing a position on the grid's xz plane
+    pub fn local_to_hex(&self, local: Vec2, size: f32) -> HexCoord {
+        let local = local / size;
+        let (q, r) = match self {
+            HexOrientation::Pointy => (SQRT_3 / 3.0 * local.x - local.y / 3.0, 2.0 / 3.0 * local.y),
+            HexOrientation::Flat => (2.0 / 3.0 * local.x, -local.x / 3.0 + SQRT_3 / 3.0 * local.y),
+        };
+        HexCoord::round(Vec2::new(q, r))
+    }
+
+    /// corners of a hex centered on the origin, counter clockwise seen from above
+    pub fn corners(&self, size: f32) -> [Vec2; 6] {
+        let first_angle = match self {
+            HexOrientation::Pointy => 30.0_f32,
+            HexOrientation::Flat => 0.0,
+        };
+        std::array::from_fn(|i| {
+            // angles run from +x towards -z, which is counter clockwise looking down on the grid
+            let angle = (first_angle + 60.0 * i as f32).to_radians();
+            Vec2::new(angle.cos(), -angle.sin()) * size
+        })
+    }
+}
+
+/// Axial position of a single hex, the third cube coordinate is implied by q + r + s = 0
+///
+/// Where a hex sits on the grid depends on the [`HexOrientation`], see
+/// [`HexOrientation::hex_to_local`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HexCoord {
+    pub q: i32,
+    pub r: i32,
+}
+
+impl HexCoord {
+    pub const ZERO: HexCoord = HexCoord { q: 0, r: 0 };
+
+    /// the six neighbouring directions, each one turned 60 degrees from the last
+    pub const DIRECTIONS: [HexCoord; 6] = [
+        HexCoord { q: 1, r: 0 },
+        HexCoord { q: 1, r: -1 },
+        HexCoord { q: 0, r: -1 },
+        HexCoord { q: -1, r: 0 },
+        HexCoord { q: -1, r: 1 },
+        HexCoord { q: 0, r: 1 },
+    ];
+
+    pub fn new(q: i32, r: i32) -> Self {
+        Self { q, r }
+    }
+
+    /// hex from cube coordinates, which have to add up to 0
+    pub fn from_cube(cube: IVec3) -> Self {
+        debug_assert_eq!(
+            cube.x + cube.y + cube.z,
+            0,
+            "cube coordinates must add up to 0"
+        );
+        Self::new(cube.x, cube.y)
+    }
+
+    /// third cube coordinate
+    pub fn s(&self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub fn as_cube(&self) -> IVec3 {
+        IVec3::new(self.q, self.r, self.s())
+    }
+
+    pub fn as_ivec2(&self) -> IVec2 {
+        IVec2::new(self.q, self.r)
+    }
+
+    /// nearest hex to fractional axial coordinates
+    pub fn round(axial: Vec2) -> Self {
+        let cube = Vec3::new(axial.x, axial.y, -axial.x - axial.y);
+        let rounded = cube.round();
+        let error = (rounded - cube).abs();
+
+        // the coordinate that rounded furthest is fixed up to keep the sum at 0
+        let (mut q, mut r) = (rounded.x, rounded.y);
+        if error.x > error.y && error.x > error.z {
+            q = -rounded.y - rounded.z;
+        } else if error.y > error.z {
+            r = -rounded.x - rounded.z;
+        }
+        Self::new(q as i32, r as i32)
+    }
+
+    pub fn neighbor(&self, direction: usize) -> HexCoord {
+        *self + Self::DIRECTIONS[direction % 6]
+    }
+
+    pub fn neighbors(&self) -> [HexCoord; 6] {
+        Self::DIRECTIONS.map(|direction| *self + direction)
+    }
+
+    /// number of steps between two hexes
+    pub fn distance(&self, other: HexCoord) -> u32 {
+        let d = (*self - other).as_cube();
+        (d.x.unsigned_abs() + d.y.unsigned_abs() + d.z.unsigned_abs()) / 2
+    }
+
+    /// hexes exactly radius steps away, going once around
+    pub fn ring(&self, radius: u32) -> Vec<HexCoord> {
+        if radius == 0 {
+            return vec![*self];
+        }
+
+        let mut hexes = Vec::with_capacity(6 * radius as usize);
+        let mut hex = *self + Self::DIRECTIONS[4] * radius as i32;
+        for direction in 0..6 {
+            for _ in 0..radius {
+                hexes.push(hex);
+                hex = hex.neighbor(direction);
+            }
+        }
+        hexes
+    }
+
+    /// hexes at most radius steps away, ring by ring from the center out
+    pub fn spiral(&self, radius: u32) -> Vec<HexCoord> {
+        (0..=radius).flat_map(|ring| self.ring(ring)).collect()
+    }
+
+    /// hexes on the straight line to other, both ends included
+    pub fn line_to(&self, other: HexCoord) -> Vec<HexCoord> {
+        let steps = self.distance(other);
+        // nudge the line off the edges between hexes so it rounds the same way every time
+        let start = self.as_ivec2().as_vec2() + Vec2::new(1e-6, 2e-6);
+        let end = other.as_ivec2().as_vec2() + Vec2::new(1e-6, 2e-6);
+        (0..=steps)
+            .map(|step| {
+                let t = if steps == 0 {
+                    0.0
+                } else {
+                    step as f32 / steps as f32
+                };
+                HexCoord::round(start.lerp(end, t))
+            })
+            .collect()
+    }
+}
+
+impl From<IVec2> for HexCoord {
+    fn from(value: IVec2) -> Self {
+        Self::new(value.x, value.y)
+    }
+}
+
+impl From<HexCoord> for IVec2 {
+    fn from(value: HexCoord) -> Self {
+        value.as_ivec2()
+    }
+}
+
+impl Add for HexCoord {
+    type Output = HexCoord;
+
+    fn add(self, rhs: HexCoord) -> HexCoord {
+        HexCoord::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl Sub for HexCoord {
+    type Output = HexCoord;
+
+    fn sub(self, rhs: HexCoord) -> HexCoord {
+        HexCoord::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl Mul<i32> for HexCoord {
+    type Output = HexCoord;
+
+    fn mul(self, rhs: i32) -> HexCoord {
+        HexCoord::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIENTATIONS: [HexOrientation; 2] = [HexOrientation::Pointy, HexOrientation::Flat];
+
+    #[test]
+    fn hex_centers_round_trip() {
+        for orientation in ORIENTATIONS {
+            for hex in HexCoord::ZERO.spiral(6) {
+                let center = orientation.hex_to_local(hex, 30.0);
+                assert_eq!(
+                    orientation.local_to_hex(center, 30.0),
+                    hex,
+                    "{orientation:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn points_inside_a_hex_round_to_it() {
+        for orientation in ORIENTATIONS {
+            for hex in HexCoord::new(3, -5).spiral(2) {
+                let center = orientation.hex_to_local(hex, 2.0);
+                // just short of each corner, well past the middle of each edge
+                for corner in orientation.corners(2.0) {
+                    let local = center + corner * 0.9;
+                    assert_eq!(orientation.local_to_hex(local, 2.0), hex, "{orientation:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn neighbors_are_one_step_and_one_hex_width_away() {
+        let hex = HexCoord::new(-2, 7);
+        let neighbors = hex.neighbors();
+        for (direction, neighbor) in neighbors.iter().enumerate() {
+            assert_eq!(hex.distance(*neighbor), 1);
+            assert_eq!(hex.neighbor(direction), *neighbor);
+            assert_eq!(hex.neighbor(direction + 6), *neighbor);
+            assert!(!neighbors[direction + 1..].contains(neighbor));
+
+            for orientation in ORIENTATIONS {
+                let step =
+                    orientation.hex_to_local(*neighbor, 1.0) - orientation.hex_to_local(hex, 1.0);
+                assert!((step.length() - SQRT_3).abs() < 1e-4, "{orientation:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn distance_counts_steps() {
+        let hex = HexCoord::new(1, 2);
+        assert_eq!(hex.distance(hex), 0);
+        assert_eq!(hex.distance(HexCoord::new(4, 2)), 3);
+        assert_eq!(hex.distance(HexCoord::new(4, -1)), 3);
+        assert_eq!(hex.distance(HexCoord::new(-2, 6)), 4);
+        assert_eq!(
+            HexCoord::new(-3, 5).distance(hex),
+            hex.distance(HexCoord::new(-3, 5))
+        );
+
+        for radius in 0..5 {
+            let ring = hex.ring(radius);
+            assert_eq!(ring.len(), (6 * radius).max(1) as usize);
+            assert!(ring.iter().all(|other| hex.distance(*other) == radius));
+        }
+    }
+}
@@ -1,39 +1,119 @@
+pub mod axis_labels;
+pub mod cell_fill;
+pub mod cell_highlight;
+pub mod cellular_automaton;
+pub mod chart;
+pub mod fog_of_war;
 pub mod grid;
+pub mod grid_coord;
+pub mod grid_nav;
+pub mod grid_terrain;
+pub mod hex_coord;
+pub mod paper_ground;
+pub mod paper_preset;
 pub mod pbr_sprite;
+pub mod plot;
 
 use std::f32::consts::PI;
 
 use bevy::{
-    pbr::{self, ExtendedMaterial},
+    pbr::ExtendedMaterial,
     prelude::*,
-    render::{
-        mesh::{MeshVertexAttributeId, VertexAttributeValues},
-        texture::{ImageLoaderSettings, ImageSampler},
-    },
+    render::texture::{ImageLoaderSettings, ImageSampler},
 };
 use grid::GridMaterial;
-use pbr_sprite::PbrPaperMaterial;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(WindowPlugin {
-            primary_window: Some(Window {
-                title: "Text Gem".to_string(),
-                ..Default::default()
-            }),
+    let mut app = App::new();
+
+    // screen space ambient occlusion doesn't support msaa
+    #[cfg(feature = "ssao")]
+    app.insert_resource(Msaa::Off);
+
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            title: "Text Gem".to_string(),
             ..Default::default()
-        }))
-        .add_plugins(grid::GridPlugin)
-        .add_plugins(pbr_sprite::PbrSpritePlugin)
-        .add_systems(Startup, init_scene)
-        .run();
+        }),
+        ..Default::default()
+    }))
+    .add_plugins(grid::GridPlugin)
+    .add_plugins(grid_coord::GridCoordPlugin)
+    .add_plugins(grid_terrain::GridTerrainPlugin)
+    .add_plugins(cell_fill::CellFillPlugin)
+    .add_plugins(cell_highlight::CellHighlightPlugin)
+    .add_plugins(fog_of_war::FogOfWarPlugin)
+    .add_plugins(cellular_automaton::CellularAutomatonPlugin)
+    .add_plugins(axis_labels::AxisLabelsPlugin)
+    .add_plugins(paper_ground::PaperGroundPlugin)
+    .add_plugins(paper_preset::PaperPresetPlugin)
+    .add_plugins(pbr_sprite::PbrSpritePlugin)
+    .add_plugins(plot::PlotPlugin)
+    .add_plugins(chart::ChartPlugin)
+    .add_systems(Startup, init_scene)
+    .add_systems(Update, ping_grid)
+    .add_systems(
+        Update,
+        route_to_hovered.after(grid_coord::HoveredCell::update),
+    )
+    .run();
+}
+
+/// Sends a ripple across the grid from the cursor on right click
+fn ping_grid(
+    mouse: Res<Input<MouseButton>>,
+    time: Res<Time>,
+    hovered: Res<grid_coord::HoveredCell>,
+    grid_query: Query<&Handle<grid::ExtendedGridMaterial>, With<paper_ground::PaperGround>>,
+    mut grid_materials: ResMut<Assets<grid::ExtendedGridMaterial>>,
+) {
+    if !mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    let Some(world_position) = hovered.world_position else {
+        return;
+    };
+
+    for material in grid_query.iter() {
+        if let Some(material) = grid_materials.get_mut(material) {
+            material.extension.ripple.start(world_position, &time);
+        }
+    }
+}
+
+/// The dashed route drawn from the paper sprite to the hovered cell
+#[derive(Component)]
+struct Route;
+
+/// Finds the way from the paper sprite to the hovered cell whenever the cursor moves to
+/// another cell, the sprite moves or the walls change
+fn route_to_hovered(
+    hovered: Res<grid_coord::HoveredCell>,
+    layout: Res<grid_coord::GridLayout>,
+    nav_grid: Res<grid_nav::NavGrid>,
+    sprite_query: Query<Ref<Transform>, With<grid_coord::SnapToGrid>>,
+    mut route_query: Query<&mut plot::Plot, With<Route>>,
+) {
+    let (Ok(sprite), Ok(mut route)) = (sprite_query.get_single(), route_query.get_single_mut())
+    else {
+        return;
+    };
+    if !hovered.is_changed() && !nav_grid.is_changed() && !sprite.is_changed() {
+        return;
+    }
+
+    let start = layout.world_to_cell(sprite.translation);
+    let path = hovered
+        .cell
+        .and_then(|goal| nav_grid.find_path(start, goal))
+        .unwrap_or_default();
+    *route = grid_nav::NavGrid::path_plot(&path).with_color(route.color);
 }
 
 fn init_scene(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut meshes: ResMut<Assets<Mesh>>,
-    mut standard_materials: ResMut<Assets<StandardMaterial>>,
     mut grid_materials: ResMut<Assets<ExtendedMaterial<StandardMaterial, GridMaterial>>>,
     mut pbr_sprite_materials: ResMut<
         Assets<ExtendedMaterial<StandardMaterial, pbr_sprite::PbrPaperMaterial>>,
@@ -41,14 +121,17 @@ fn init_scene(
 ) {
     let graph_blue = Color::rgba(0.19, 0.51, 1.0, 1.0);
     let light_grey = Color::rgba(0.85, 0.85, 0.92, 1.0);
+    let dark_blue = Color::rgba(0.08, 0.27, 0.72, 1.0);
+    let axis_red = Color::rgba(0.85, 0.18, 0.18, 1.0);
+    let axis_green = Color::rgba(0.12, 0.55, 0.25, 1.0);
 
     // Setup CameraTarget
     let mut camera_target = grid::CameraTarget::default()
         .looking_at(Vec3::new(0.0, 15.0, 0.0))
         .with_up(Vec3::Y)
         .with_bounding_box(grid::BoundingBox::new(
-            Vec3::new(-3000.0, 15.0, -3000.0),
-            Vec3::new(3000.0, 4000.0, 3000.0),
+            Vec3::new(-100000.0, 15.0, -100000.0),
+            Vec3::new(100000.0, 4000.0, 100000.0),
         ))
         .rotating(PI / -4.0);
 
@@ -66,7 +149,11 @@ fn init_scene(
     }
 
     // Camera
-    commands.spawn((Camera3dBundle::default(), camera_target));
+    #[cfg_attr(not(feature = "ssao"), allow(unused_variables, unused_mut))]
+    let mut camera = commands.spawn((Camera3dBundle::default(), camera_target));
+    // the grid writes into the depth and normal prepasses, so it picks up ambient occlusion
+    #[cfg(feature = "ssao")]
+    camera.insert(bevy::pbr::ScreenSpaceAmbientOcclusionBundle::default());
 
     // Light
     commands.spawn(DirectionalLightBundle {
@@ -80,50 +167,310 @@ fn init_scene(
         ..default()
     });
 
-    // Grid Box
+    // Cell Fills
+    let mut cell_fills =
+        cell_fill::CellFills::new(grid_coord::GridCoord::new(-100, -100), UVec2::new(200, 200));
+    for x in 2..7 {
+        for y in 2..7 {
+            cell_fills.set(
+                grid_coord::GridCoord::new(x, y),
+                cell_fill::CellFill::solid(Color::rgba(0.2, 0.7, 0.3, 0.6))
+                    .with_pattern(cell_fill::CellPattern::Hatched),
+            );
+        }
+    }
+
+    // Obstacles, a wall and the garden above block the way, and so does the triplanar block.
+    // the paper sprite walks around them to the hovered cell
+    let mut nav_grid = grid_nav::NavGrid::new(IRect::new(-40, -40, 40, 40)).with_diagonal(true);
+    let wall = (-8..4).map(|x| grid_coord::GridCoord::new(x, -3));
+    let garden = (2..7).flat_map(|x| (2..7).map(move |y| grid_coord::GridCoord::new(x, y)));
+    let block = (-6..-3).flat_map(|x| (2..4).map(move |y| grid_coord::GridCoord::new(x, y)));
+    for cell in wall {
+        cell_fills.set(
+            cell,
+            cell_fill::CellFill::solid(Color::rgba(0.3, 0.3, 0.35, 0.7))
+                .with_pattern(cell_fill::CellPattern::CrossHatched),
+        );
+        nav_grid.block(cell);
+    }
+    for cell in garden.chain(block) {
+        nav_grid.block(cell);
+    }
+    commands.insert_resource(nav_grid);
+    commands.spawn((
+        plot::Plot::polyline(Vec::new()).with_color(dark_blue),
+        Route,
+    ));
+
+    // Cell Highlights
+    let mut cell_highlights = cell_highlight::CellHighlights::new()
+        .with_hover(cell_highlight::HighlightStyle::outline(dark_blue))
+        .with_selection(cell_highlight::HighlightStyle::pulse(Color::rgba(
+            0.95, 0.6, 0.1, 1.0,
+        )));
+
+    // Cellular Automaton, a wrapping Game of Life below the wall. space pauses, period steps,
+    // backspace clears, R scatters cells and the middle mouse button paints them
+    let mut automaton = cellular_automaton::CellularAutomaton::new(
+        grid_coord::GridCoord::new(-16, -48),
+        UVec2::new(32, 32),
+    )
+    .with_wrap(true)
+    .running();
+    let glider = ".O\n..O\nOOO";
+    for offset in [IVec2::new(2, 2), IVec2::new(12, 4), IVec2::new(22, 8)] {
+        automaton.stamp(offset, glider);
+    }
+    automaton.stamp(IVec2::new(14, 20), ".OO\nOO\n.O");
+    cell_highlights.add(cell_highlight::CellHighlight::rect(
+        automaton.origin(),
+        automaton.origin() + (automaton.size().as_ivec2() - IVec2::ONE).into(),
+        cell_highlight::HighlightStyle::outline(Color::rgba(0.08, 0.27, 0.72, 0.5)),
+    ));
+
+    // Paper Ground, chunks of it are spawned around the camera target as it moves
+    commands.spawn((
+        SpatialBundle::from_transform(Transform::from_xyz(0.0, 15.0, 0.0)),
+        grid_materials.add(ExtendedMaterial {
+            base: light_grey.into(),
+            extension: GridMaterial {
+                color: graph_blue,
+                subdivisions: UVec2::new(0, 0),
+                line_widths: Vec2::new(0.01, 0.01),
+                major_lines: grid::GridLines::every(
+                    UVec2::new(5, 5),
+                    dark_blue,
+                    Vec2::new(0.03, 0.03),
+                ),
+                axis_lines: grid::GridLines::axis(axis_red, Vec2::new(0.05, 0.05)),
+                y_axis_color: axis_green,
+                fade: grid::GridFade::default()
+                    .with_spacing(3.0, 6.0)
+                    .with_distance(4000.0, 8000.0),
+                ripple: grid::GridRipple::new(axis_red, 600.0, 40.0, 2.0),
+                // draw the sheet on from the origin as the scene opens
+                reveal: grid::GridReveal::radial(Vec3::ZERO, 1200.0).with_softness(400.0),
+                paper: grid::GridPaper::new(7)
+                    .with_fibers(0.06, 0.08)
+                    .with_wrinkles(0.4, 14.0)
+                    .with_stains(Color::rgba(0.72, 0.52, 0.3, 0.8), 0.15, 12.0),
+                ..Default::default()
+            },
+        }),
+        paper_ground::PaperGround::new(30.0)
+            .with_lod(4, 1500.0)
+            .with_view_distance(8000.0),
+        cell_fills,
+        cell_highlights,
+        automaton,
+        // the maze around the paper sprite is blank until the sprite has seen it, the wall
+        // keeps it from seeing what lies behind
+        fog_of_war::FogOfWar::new(grid_coord::GridCoord::new(-12, -12), UVec2::new(24, 24))
+            .with_outside(fog_of_war::FogState::Visible),
+    ));
+
+    // Triplanar Block, its cells line up with the 30 unit cells of the ground
+    commands.spawn(MaterialMeshBundle {
+        mesh: meshes.add(shape::Box::new(90.0, 60.0, 60.0).into()),
+        material: grid_materials.add(ExtendedMaterial {
+            base: Color::WHITE.into(),
+            extension: GridMaterial {
+                color: graph_blue,
+                line_widths: Vec2::new(0.02, 0.02),
+                mapping: grid::GridMapping::Triplanar,
+                triplanar: grid::GridTriplanar::new(30.0),
+                ..Default::default()
+            },
+        }),
+        transform: Transform::from_xyz(-135.0, 45.0, 90.0)
+            .with_rotation(Quat::from_rotation_y(PI / 8.0)),
+        ..Default::default()
+    });
+
+    // Axis Labels, numbered at every major line of the ground
+    commands.insert_resource(
+        axis_labels::AxisLabels::new(asset_server.load("fonts/CourierPrime-Regular.ttf"))
+            .with_colors(axis_red, axis_green)
+            .with_interval(5),
+    );
+
+    // Plots, inked onto the ground in grid cells
+    commands.spawn(
+        plot::Plot::function(|x| 3.0 * (x * 0.5).sin(), -40.0..40.0)
+            .with_color(Color::rgba(0.3, 0.12, 0.45, 1.0))
+            .with_thickness(0.12),
+    );
+    commands.spawn(
+        plot::Plot::parametric(
+            |t| Vec2::new(-20.0 + 6.0 * (3.0 * t).sin(), 12.0 + 6.0 * (2.0 * t).sin()),
+            0.0..2.0 * PI,
+        )
+        .with_samples(512)
+        .with_color(Color::rgba(0.1, 0.2, 0.5, 1.0)),
+    );
+    commands.spawn(
+        plot::Plot::function(f32::tan, -40.0..40.0)
+            .with_samples(1024)
+            .with_clip(Rect::new(-40.0, -8.0, 40.0, 8.0))
+            .with_color(axis_red),
+    );
+
+    // Charts, a week of metrics read from a csv file. requests stand as bars along the x axis,
+    // latency is inked on the paper beside them and errors are scattered against requests
+    let metrics: Handle<chart::ChartData> = asset_server.load("charts/weekly_metrics.csv");
+    commands.spawn((
+        SpatialBundle::default(),
+        chart::Chart::new(chart::ChartKind::Bar, metrics.clone(), "requests")
+            .with_x_column("week")
+            .with_origin(Vec2::new(10.0, 12.0))
+            .with_scale(chart::ChartScale::Fit(Vec2::new(12.0, 8.0)))
+            .with_color(light_grey),
+    ));
+    commands.spawn((
+        SpatialBundle::default(),
+        chart::Chart::new(chart::ChartKind::Line, metrics.clone(), "latency_ms")
+            .with_x_column("week")
+            .with_origin(Vec2::new(10.0, 12.0))
+            .with_scale(chart::ChartScale::UnitsPerCell(Vec2::new(1.0, 20.0)))
+            .with_color(dark_blue),
+    ));
+    commands.spawn((
+        SpatialBundle::default(),
+        chart::Chart::new(chart::ChartKind::Scatter, metrics, "errors, per 1000")
+            .with_x_column("requests")
+            .with_origin(Vec2::new(-30.0, 12.0))
+            .with_scale(chart::ChartScale::Fit(Vec2::new(12.0, 10.0)))
+            .with_color(axis_red),
+    ));
+
+    // Hills, the terrain meets the ground at its edges and keeps its 30 unit cells. it sits a
+    // unit above the ground so the two don't fight where the hills flatten out
+    commands.spawn((
+        MaterialMeshBundle {
+            material: grid_materials.add(ExtendedMaterial {
+                base: light_grey.into(),
+                extension: GridMaterial {
+                    color: graph_blue,
+                    line_widths: Vec2::new(0.02, 0.02),
+                    // drawn on from its western edge as the scene opens
+                    reveal: grid::GridReveal::sweep(Vec3::new(-1650.0, 0.0, 0.0), Vec3::X, 300.0)
+                        .with_softness(150.0),
+                    ..Default::default()
+                },
+            }),
+            transform: Transform::from_xyz(-1200.0, 16.0, -900.0),
+            ..Default::default()
+        },
+        grid_terrain::PendingGridTerrain {
+            heightmap: asset_server.load("heightmaps/hills.png"),
+            size: Vec3::new(900.0, 240.0, 900.0),
+            subdivisions: UVec2::new(29, 29),
+        },
+    ));
+
+    // Hex Board, the hex pattern of its material lines up with the edges of the mesh
     commands.spawn(MaterialMeshBundle {
         mesh: meshes.add(
-            grid::GridBox {
-                size: Vec3::new(6000.0, 30.0, 6000.0),
-                subdivisions: UVec3::new(200, 0, 200),
+            grid::HexGrid {
+                orientation: hex_coord::HexOrientation::Flat,
+                size: 30.0,
+                shape: grid::HexShape::Hexagon { radius: 5 },
             }
             .into(),
         ),
         material: grid_materials.add(ExtendedMaterial {
             base: light_grey.into(),
             extension: GridMaterial {
-                color: graph_blue.into(),
-                subdivisions: UVec2::new(0, 0),
-                line_widths: Vec2::new(0.01, 0.01),
+                color: dark_blue,
+                line_widths: Vec2::new(0.04, 0.04),
+                pattern: grid::GridPattern::Hex,
+                ..Default::default()
             },
         }),
+        transform: Transform::from_xyz(-900.0, 16.0, 900.0),
         ..Default::default()
     });
 
-    // PBR Sprite
-    let image: Handle<Image> =
-        asset_server.load_with_settings("goomba.png", |settings: &mut ImageLoaderSettings| {
-            settings.sampler = ImageSampler::nearest();
-        });
+    // Surface Plot, a ripple spreading out from the plot's origin. each unit is a 30 unit cell
+    // and the paper is tinted from blue in the troughs to red on the crest, with a contour
+    // line every half unit of height
+    let surface_transform = Transform::from_xyz(1100.0, 16.0, 800.0).with_scale(Vec3::splat(30.0));
     commands.spawn(MaterialMeshBundle {
         mesh: meshes.add(
-            pbr_sprite::PaperSprite(pbr_sprite::QuadSprite::new(Vec2::new(32.0, 32.0))).into(),
+            plot::SurfacePlot::new(
+                |p: Vec2| {
+                    let r = p.length();
+                    2.0 + 2.0 * r.cos() / (1.0 + 0.15 * r * r)
+                },
+                Rect::new(-8.0, -8.0, 8.0, 8.0),
+            )
+            .with_resolution(UVec2::new(96, 96))
+            .into(),
         ),
-        material: pbr_sprite_materials.add(ExtendedMaterial {
-            base: StandardMaterial {
-                base_color: Color::WHITE,
-                base_color_texture: Some(image),
-                alpha_mode: AlphaMode::Mask(0.2),
+        material: grid_materials.add(ExtendedMaterial {
+            base: light_grey.into(),
+            extension: GridMaterial {
+                color: graph_blue,
+                line_widths: Vec2::new(0.02, 0.02),
+                axis_lines: grid::GridLines::axis(axis_red, Vec2::new(0.05, 0.05)),
+                y_axis_color: axis_green,
+                height: grid::GridHeight::from_transform(&surface_transform)
+                    .with_colors(dark_blue.with_a(0.35), axis_red.with_a(0.35), 1.0, 4.0)
+                    .with_contours(dark_blue, 0.5, 0.03),
                 ..Default::default()
             },
-            extension: pbr_sprite::PbrPaperMaterial {
-                uv_scale: Vec2::new(1.0, 1.0),
-                uv_translate: Vec2::new(0.0, 0.0),
-                outline_thickness: 0.05,
-                outline_color: Color::WHITE,
-            },
         }),
-        transform: Transform::from_xyz(0.0, 30.0, 0.0),
+        transform: surface_transform,
         ..Default::default()
     });
+
+    // Notebook Sheet, its look comes from a preset file. it is not a GridBox entity as the
+    // grid layout follows the ground. its rounded edges make it read as a thick card
+    let sheet =
+        grid::GridBox::new(Vec3::new(720.0, 2.0, 960.0), UVec3::new(23, 0, 31)).with_bevel(0.8, 4);
+    commands.spawn((
+        MaterialMeshBundle {
+            mesh: meshes.add(sheet.into()),
+            material: grid_materials.add(ExtendedMaterial {
+                base: Color::WHITE.into(),
+                extension: GridMaterial::default(),
+            }),
+            transform: Transform::from_xyz(900.0, 16.0, -600.0)
+                .with_rotation(Quat::from_rotation_y(PI / 12.0)),
+            ..Default::default()
+        },
+        asset_server.load::<paper_preset::PaperPreset>("presets/college_ruled.paper.ron"),
+    ));
+
+    // PBR Sprite, it stands in the middle of a cell however it is moved
+    let image: Handle<Image> =
+        asset_server.load_with_settings("goomba.png", |settings: &mut ImageLoaderSettings| {
+            settings.sampler = ImageSampler::nearest();
+        });
+    commands.spawn((
+        MaterialMeshBundle {
+            mesh: meshes.add(
+                pbr_sprite::PaperSprite(pbr_sprite::QuadSprite::new(Vec2::new(32.0, 32.0))).into(),
+            ),
+            material: pbr_sprite_materials.add(ExtendedMaterial {
+                base: StandardMaterial {
+                    base_color: Color::WHITE,
+                    base_color_texture: Some(image),
+                    alpha_mode: AlphaMode::Mask(0.2),
+                    ..Default::default()
+                },
+                extension: pbr_sprite::PbrPaperMaterial {
+                    uv_scale: Vec2::new(1.0, 1.0),
+                    uv_translate: Vec2::new(0.0, 0.0),
+                    outline_thickness: 0.05,
+                    outline_color: Color::WHITE,
+                },
+            }),
+            transform: Transform::from_xyz(0.0, 30.0, 0.0),
+            ..Default::default()
+        },
+        grid_coord::SnapToGrid::centers(),
+        fog_of_war::VisionSource::new(6.0),
+    ));
 }
@@ -0,0 +1,259 @@
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    grid::{CameraTarget, ExtendedGridMaterial, GridChunk, GridMaterial},
+    grid_coord::GridLayout,
+};
+
+pub struct PaperGroundPlugin;
+
+impl Plugin for PaperGroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            PaperGround::update_chunks.after(CameraTarget::update),
+        );
+    }
+}
+
+/// Finest chunks are clamped to this many cells a side and coarser levels are dropped once
+/// they would grow past it, so chunk sizes and cell positions stay well within an i32
+const MAX_CHUNK_CELLS: u32 = 1 << 20;
+
+/// A chunk of ground spawned by a [`PaperGround`]
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroundChunk {
+    /// 0 for the finest chunks, every level above doubles the size of the chunk
+    pub level: u32,
+
+    /// position of the chunk counted in chunks of its own level
+    pub coord: IVec2,
+}
+
+/// Endless flat paper that follows the look_at point of the [`CameraTarget`]
+///
+/// The ground is split into chunks which are spawned as children of this entity as the camera
+/// moves and despawned once they fall out of view. Every chunk draws with the grid material on
+/// this entity and counts its uvs from the same origin, so lines run on across chunk borders.
+/// Chunks further from the camera cover more ground with the same number of vertices, each
+/// level of detail doubling their size and halving the density of their mesh.
+///
+/// The ground lies in the local xz plane of its entity, with uv cell (0, 0) starting at the
+/// entity's translation. The [`GridLayout`] follows the ground as long as there is no
+/// [`crate::grid::GridBox`] entity besides it.
+#[derive(Component, Debug, Clone)]
+pub struct PaperGround {
+    /// world size of a single uv cell, the grid material divides it further
+    pub cell_size: f32,
+
+    /// number of uv cells along each side of the finest chunks, which is also the number of
+    /// quads along each side of every chunk's mesh
+    pub chunk_cells: u32,
+
+    /// number of chunk sizes, 1 gives every chunk the same size. levels that would make a chunk
+    /// more than 2^20 cells wide are left out
+    pub lod_levels: u32,
+
+    /// distance from the camera target within which the finest chunks are used,
+    /// it doubles for every coarser level
+    pub detail_distance: f32,
+
+    /// chunks further than this from the camera target are not spawned
+    pub view_distance: f32,
+
+    chunks: HashMap<GroundChunk, Entity>,
+}
+
+impl Default for PaperGround {
+    fn default() -> Self {
+        Self {
+            cell_size: 30.0,
+            chunk_cells: 16,
+            lod_levels: 4,
+            detail_distance: 1000.0,
+            view_distance: 8000.0,
+            chunks: HashMap::default(),
+        }
+    }
+}
+
+impl PaperGround {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_chunk_cells(mut self, chunk_cells: u32) -> Self {
+        self.chunk_cells = chunk_cells;
+        self
+    }
+
+    pub fn with_lod(mut self, lod_levels: u32, detail_distance: f32) -> Self {
+        self.lod_levels = lod_levels;
+        self.detail_distance = detail_distance;
+        self
+    }
+
+    pub fn with_view_distance(mut self, view_distance: f32) -> Self {
+        self.view_distance = view_distance;
+        self
+    }
+
+    fn chunk_cells(&self) -> u32 {
+        self.chunk_cells.clamp(1, MAX_CHUNK_CELLS)
+    }
+
+    /// coarsest level of detail that is used
+    fn top_level(&self) -> u32 {
+        let max_level = (MAX_CHUNK_CELLS / self.chunk_cells()).ilog2();
+        (self.lod_levels.max(1) - 1).min(max_level)
+    }
+
+    /// number of uv cells along each side of a chunk, level is at most [`Self::top_level`]
+    fn cells(&self, level: u32) -> u32 {
+        self.chunk_cells() << level
+    }
+
+    fn mesh(&self, chunk: GroundChunk) -> GridChunk {
+        let cells = self.cells(chunk.level);
+        GridChunk {
+            min: chunk.coord * cells as i32,
+            cells: UVec2::splat(cells),
+            cell_size: self.cell_size,
+            quads: UVec2::splat(self.chunk_cells()),
+        }
+    }
+
+    /// Layout of the cells drawn by `material` on the ground
+    pub fn layout(&self, material: &GridMaterial, transform: &GlobalTransform) -> GridLayout {
+        let (scale, rotation, translation) = transform.to_scale_rotation_translation();
+        let cell_size = Vec2::splat(self.cell_size) * Vec2::new(scale.x, scale.z)
+            / (material.subdivisions + 1).as_vec2();
+        let offset = material.axis_lines.offset * cell_size;
+        GridLayout {
+            origin: translation + rotation * Vec3::new(offset.x, 0.0, offset.y),
+            cell_size,
+            rotation,
+            ..Default::default()
+        }
+    }
+
+    /// Chunks covering the ground around focus, in the ground's local space,
+    /// finer chunks closer to it
+    pub fn wanted_chunks(&self, focus: Vec3) -> Vec<GroundChunk> {
+        let focus = Vec2::new(focus.x, focus.z) / self.cell_size;
+        let view_distance = self.view_distance / self.cell_size;
+
+        let top_level = self.top_level();
+        let top_cells = self.cells(top_level) as f32;
+        let first = ((focus - view_distance) / top_cells).floor().as_ivec2();
+        let last = ((focus + view_distance) / top_cells).floor().as_ivec2();
+
+        let mut chunks = Vec::new();
+        for y in first.y..=last.y {
+            for x in first.x..=last.x {
+                self.split(
+                    GroundChunk {
+                        level: top_level,
+                        coord: IVec2::new(x, y),
+                    },
+                    focus,
+                    &mut chunks,
+                );
+            }
+        }
+        chunks
+    }
+
+    /// adds chunk to chunks, or its four quarters if it is close enough to the focus
+    fn split(&self, chunk: GroundChunk, focus: Vec2, chunks: &mut Vec<GroundChunk>) {
+        // distances in uv cells
+        let cells = self.cells(chunk.level) as f32;
+        let min = chunk.coord.as_vec2() * cells;
+        let distance = focus.distance(focus.clamp(min, min + cells));
+        if distance > self.view_distance / self.cell_size {
+            return;
+        }
+
+        let detail_distance = self.detail_distance / self.cell_size;
+        if chunk.level == 0 || distance >= detail_distance * (1 << (chunk.level - 1)) as f32 {
+            chunks.push(chunk);
+            return;
+        }
+
+        for offset in [IVec2::ZERO, IVec2::X, IVec2::Y, IVec2::ONE] {
+            self.split(
+                GroundChunk {
+                    level: chunk.level - 1,
+                    coord: chunk.coord * 2 + offset,
+                },
+                focus,
+                chunks,
+            );
+        }
+    }
+
+    /// Spawns the chunks around the camera target and despawns the ones no longer needed
+    pub fn update_chunks(
+        mut commands: Commands,
+        camera_query: Query<&CameraTarget>,
+        mut ground_query: Query<(
+            Entity,
+            &mut PaperGround,
+            &GlobalTransform,
+            &Handle<ExtendedGridMaterial>,
+        )>,
+        mut meshes: ResMut<Assets<Mesh>>,
+    ) {
+        let Ok(camera_target) = camera_query.get_single() else {
+            return;
+        };
+        let focus = camera_target.get_look_at();
+
+        for (entity, mut ground, transform, material) in ground_query.iter_mut() {
+            let local_focus = transform.affine().inverse().transform_point3(focus);
+            let wanted = ground.wanted_chunks(local_focus);
+            if wanted.len() == ground.chunks.len()
+                && wanted.iter().all(|chunk| ground.chunks.contains_key(chunk))
+            {
+                continue;
+            }
+
+            let mut chunks = HashMap::default();
+            for chunk in wanted {
+                let chunk_entity = match ground.chunks.remove(&chunk) {
+                    Some(chunk_entity) => chunk_entity,
+                    None => {
+                        let mesh = ground.mesh(chunk);
+                        let translation = mesh.min.as_vec2() * ground.cell_size;
+                        commands
+                            .spawn((
+                                MaterialMeshBundle {
+                                    mesh: meshes.add(mesh.into()),
+                                    material: material.clone(),
+                                    transform: Transform::from_xyz(
+                                        translation.x,
+                                        0.0,
+                                        translation.y,
+                                    ),
+                                    ..Default::default()
+                                },
+                                chunk,
+                            ))
+                            .set_parent(entity)
+                            .id()
+                    }
+                };
+                chunks.insert(chunk, chunk_entity);
+            }
+
+            // whatever is left over has been replaced or moved out of view
+            for chunk_entity in ground.chunks.values() {
+                commands.entity(*chunk_entity).despawn_recursive();
+            }
+            ground.chunks = chunks;
+        }
+    }
+}
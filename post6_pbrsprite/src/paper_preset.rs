@@ -0,0 +1,152 @@
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::{BoxedFuture, HashSet},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::grid::{ExtendedGridMaterial, GridHoles, GridLines, GridPaper, GridPattern};
+
+pub struct PaperPresetPlugin;
+
+impl Plugin for PaperPresetPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<PaperPreset>()
+            .init_asset_loader::<PaperPresetLoader>()
+            .add_systems(Update, PaperPreset::apply_loaded);
+    }
+}
+
+/// A kind of paper for a [`crate::grid::GridMaterial`]
+///
+/// Presets are loaded from `.paper.ron` files. An entity holding both a `Handle<PaperPreset>`
+/// and a grid material has the preset applied to its material once the preset loads, and again
+/// whenever the file changes. Only the look of the paper is replaced, cell fills, highlights
+/// and animations are left alone.
+#[derive(Asset, TypePath, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PaperPreset {
+    /// color of the blank sheet
+    pub paper_color: Color,
+
+    pub pattern: GridPattern,
+
+    /// color of the minor lines
+    pub line_color: Color,
+
+    /// width of the minor lines along each axis, 0 leaves the lines crossing that axis out
+    pub line_widths: Vec2,
+
+    pub major_lines: GridLines,
+    pub margin_lines: GridLines,
+    pub holes: GridHoles,
+    pub paper: GridPaper,
+}
+
+impl Default for PaperPreset {
+    fn default() -> Self {
+        Self::graph()
+    }
+}
+
+impl PaperPreset {
+    /// blue squared graph paper, fields left out of a preset file keep these values
+    pub fn graph() -> Self {
+        Self {
+            paper_color: Color::rgba(0.85, 0.85, 0.92, 1.0),
+            pattern: GridPattern::Lines,
+            line_color: Color::rgba(0.19, 0.51, 1.0, 1.0),
+            line_widths: Vec2::new(0.01, 0.01),
+            major_lines: GridLines::default(),
+            margin_lines: GridLines::default(),
+            holes: GridHoles::default(),
+            paper: GridPaper::default(),
+        }
+    }
+
+    /// replaces the look of the paper in material with this preset
+    pub fn apply(&self, material: &mut ExtendedGridMaterial) {
+        material.base.base_color = self.paper_color;
+        // holes are cut out by discarding, which opaque materials never do
+        material.base.alpha_mode = if self.holes.count > 0 {
+            AlphaMode::Mask(0.5)
+        } else {
+            AlphaMode::Opaque
+        };
+
+        let grid = &mut material.extension;
+        grid.pattern = self.pattern;
+        grid.color = self.line_color;
+        grid.line_widths = self.line_widths;
+        grid.major_lines = self.major_lines;
+        grid.margin_lines = self.margin_lines;
+        grid.holes = self.holes;
+        grid.paper = self.paper;
+    }
+
+    /// Applies presets to the grid materials of their entities as they load or change
+    pub fn apply_loaded(
+        mut events: EventReader<AssetEvent<PaperPreset>>,
+        presets: Res<Assets<PaperPreset>>,
+        preset_query: Query<(Ref<Handle<PaperPreset>>, &Handle<ExtendedGridMaterial>)>,
+        mut materials: ResMut<Assets<ExtendedGridMaterial>>,
+    ) {
+        let changed: HashSet<AssetId<PaperPreset>> = events
+            .read()
+            .filter_map(|event| match event {
+                AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                    Some(*id)
+                }
+                _ => None,
+            })
+            .collect();
+
+        for (preset, material) in preset_query.iter() {
+            if !preset.is_changed() && !changed.contains(&preset.id()) {
+                continue;
+            }
+            let Some(preset) = presets.get(&*preset) else {
+                continue;
+            };
+            if let Some(material) = materials.get_mut(material) {
+                preset.apply(material);
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum PaperPresetLoaderError {
+    #[error("could not read paper preset: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("could not parse paper preset: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+}
+
+/// Loads [`PaperPreset`]s from RON files
+#[derive(Default)]
+pub struct PaperPresetLoader;
+
+impl AssetLoader for PaperPresetLoader {
+    type Asset = PaperPreset;
+    type Settings = ();
+    type Error = PaperPresetLoaderError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<PaperPreset, PaperPresetLoaderError>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            Ok(ron::de::from_bytes(&bytes)?)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["paper.ron"]
+    }
+}
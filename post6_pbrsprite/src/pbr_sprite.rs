@@ -11,9 +11,11 @@ pub struct PbrSpritePlugin;
 
 impl Plugin for PbrSpritePlugin {
     fn build(&self, app: &mut App) {
-        let mut material_plugin =
-            MaterialPlugin::<ExtendedMaterial<StandardMaterial, PbrPaperMaterial>>::default();
-        material_plugin.prepass_enabled = true;
+        let material_plugin =
+            MaterialPlugin::<ExtendedMaterial<StandardMaterial, PbrPaperMaterial>> {
+                prepass_enabled: true,
+                ..Default::default()
+            };
 
         app.add_plugins(material_plugin);
     }
@@ -59,30 +61,85 @@ impl From<QuadSprite> for Mesh {
         let extent_y = quad.size.y / 2.0;
 
         let (u_left, u_right) = if quad.flip { (1.0, 0.0) } else { (0.0, 1.0) };
+
+        // u runs along the x axis (reversed when flipped) and v runs down the y axis,
+        // w is picked so that the bitangent (w * normal x tangent) points along -v
+        let front_tangent = if quad.flip {
+            [-1.0, 0.0, 0.0, -1.0]
+        } else {
+            [1.0, 0.0, 0.0, 1.0]
+        };
+        let back_tangent = if quad.flip {
+            [-1.0, 0.0, 0.0, 1.0]
+        } else {
+            [1.0, 0.0, 0.0, -1.0]
+        };
         let vertices = [
             // Front Face
-            ([-extent_x, -extent_y, 0.0], [0.0, 0.0, 1.0], [u_left, 1.0]),
-            ([-extent_x, extent_y, 0.0], [0.0, 0.0, 1.0], [u_left, 0.0]),
-            ([extent_x, extent_y, 0.0], [0.0, 0.0, 1.0], [u_right, 0.0]),
-            ([extent_x, -extent_y, 0.0], [0.0, 0.0, 1.0], [u_right, 1.0]),
+            (
+                [-extent_x, -extent_y, 0.0],
+                [0.0, 0.0, 1.0],
+                [u_left, 1.0],
+                front_tangent,
+            ),
+            (
+                [-extent_x, extent_y, 0.0],
+                [0.0, 0.0, 1.0],
+                [u_left, 0.0],
+                front_tangent,
+            ),
+            (
+                [extent_x, extent_y, 0.0],
+                [0.0, 0.0, 1.0],
+                [u_right, 0.0],
+                front_tangent,
+            ),
+            (
+                [extent_x, -extent_y, 0.0],
+                [0.0, 0.0, 1.0],
+                [u_right, 1.0],
+                front_tangent,
+            ),
             // Back Face
-            ([-extent_x, -extent_y, 0.0], [0.0, 0.0, -1.0], [u_left, 1.0]),
-            ([-extent_x, extent_y, 0.0], [0.0, 0.0, -1.0], [u_left, 0.0]),
-            ([extent_x, extent_y, 0.0], [0.0, 0.0, -1.0], [u_right, 0.0]),
-            ([extent_x, -extent_y, 0.0], [0.0, 0.0, -1.0], [u_right, 1.0]),
+            (
+                [-extent_x, -extent_y, 0.0],
+                [0.0, 0.0, -1.0],
+                [u_left, 1.0],
+                back_tangent,
+            ),
+            (
+                [-extent_x, extent_y, 0.0],
+                [0.0, 0.0, -1.0],
+                [u_left, 0.0],
+                back_tangent,
+            ),
+            (
+                [extent_x, extent_y, 0.0],
+                [0.0, 0.0, -1.0],
+                [u_right, 0.0],
+                back_tangent,
+            ),
+            (
+                [extent_x, -extent_y, 0.0],
+                [0.0, 0.0, -1.0],
+                [u_right, 1.0],
+                back_tangent,
+            ),
         ];
 
         let indices = Indices::U32(vec![0, 2, 1, 0, 3, 2, 6, 4, 5, 7, 4, 6]);
 
-        let positions: Vec<_> = vertices.iter().map(|(p, _, _)| *p).collect();
-        let normals: Vec<_> = vertices.iter().map(|(_, n, _)| *n).collect();
-        let uvs: Vec<_> = vertices.iter().map(|(_, _, uv)| *uv).collect();
+        let positions: Vec<_> = vertices.iter().map(|(p, _, _, _)| *p).collect();
+        let normals: Vec<_> = vertices.iter().map(|(_, n, _, _)| *n).collect();
+        let uvs: Vec<_> = vertices.iter().map(|(_, _, uv, _)| *uv).collect();
+        let tangents: Vec<_> = vertices.iter().map(|(_, _, _, t)| *t).collect();
 
         Mesh::new(PrimitiveTopology::TriangleList)
             .with_indices(Some(indices))
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
             .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
             .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
     }
 }
 
@@ -96,29 +153,79 @@ impl From<PaperSprite> for Mesh {
         let extent_y = quad.size.y / 2.0;
 
         let (u_left, u_right) = if quad.flip { (1.0, 0.0) } else { (0.0, 1.0) };
+
+        // the normals point up, so shade the sprite as if it were laid back flat on
+        // the ground: u runs along the x axis and v runs along the z axis
+        let tangent = if quad.flip {
+            [-1.0, 0.0, 0.0, -1.0]
+        } else {
+            [1.0, 0.0, 0.0, 1.0]
+        };
         let vertices = [
             // Front Face
-            ([-extent_x, -extent_y, 0.0], [0.0, 1.0, 0.0], [u_left, 1.0]),
-            ([-extent_x, extent_y, 0.0], [0.0, 1.0, 0.0], [u_left, 0.0]),
-            ([extent_x, extent_y, 0.0], [0.0, 1.0, 0.0], [u_right, 0.0]),
-            ([extent_x, -extent_y, 0.0], [0.0, 1.0, 0.0], [u_right, 1.0]),
+            (
+                [-extent_x, -extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_left, 1.0],
+                tangent,
+            ),
+            (
+                [-extent_x, extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_left, 0.0],
+                tangent,
+            ),
+            (
+                [extent_x, extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_right, 0.0],
+                tangent,
+            ),
+            (
+                [extent_x, -extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_right, 1.0],
+                tangent,
+            ),
             // Back Face
-            ([-extent_x, -extent_y, 0.0], [0.0, 1.0, 0.0], [u_left, 1.0]),
-            ([-extent_x, extent_y, 0.0], [0.0, 1.0, 0.0], [u_left, 0.0]),
-            ([extent_x, extent_y, 0.0], [0.0, 1.0, 0.0], [u_right, 0.0]),
-            ([extent_x, -extent_y, 0.0], [0.0, 1.0, 0.0], [u_right, 1.0]),
+            (
+                [-extent_x, -extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_left, 1.0],
+                tangent,
+            ),
+            (
+                [-extent_x, extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_left, 0.0],
+                tangent,
+            ),
+            (
+                [extent_x, extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_right, 0.0],
+                tangent,
+            ),
+            (
+                [extent_x, -extent_y, 0.0],
+                [0.0, 1.0, 0.0],
+                [u_right, 1.0],
+                tangent,
+            ),
         ];
 
         let indices = Indices::U32(vec![0, 2, 1, 0, 3, 2, 6, 4, 5, 7, 4, 6]);
 
-        let positions: Vec<_> = vertices.iter().map(|(p, _, _)| *p).collect();
-        let normals: Vec<_> = vertices.iter().map(|(_, n, _)| *n).collect();
-        let uvs: Vec<_> = vertices.iter().map(|(_, _, uv)| *uv).collect();
+        let positions: Vec<_> = vertices.iter().map(|(p, _, _, _)| *p).collect();
+        let normals: Vec<_> = vertices.iter().map(|(_, n, _, _)| *n).collect();
+        let uvs: Vec<_> = vertices.iter().map(|(_, _, uv, _)| *uv).collect();
+        let tangents: Vec<_> = vertices.iter().map(|(_, _, _, t)| *t).collect();
 
         Mesh::new(PrimitiveTopology::TriangleList)
             .with_indices(Some(indices))
             .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
             .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
             .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
     }
 }
@@ -0,0 +1,459 @@
+use std::{ops::Range, sync::Arc};
+
+use bevy::{
+    pbr::{ExtendedMaterial, MaterialExtension, NotShadowCaster},
+    prelude::*,
+    render::{
+        mesh::Indices,
+        render_resource::{AsBindGroup, PrimitiveTopology, ShaderRef},
+    },
+};
+
+use crate::grid_coord::GridLayout;
+
+pub struct PlotPlugin;
+
+impl Plugin for PlotPlugin {
+    fn build(&self, app: &mut App) {
+        // ink is blended over the paper, which keeps it out of the depth prepass anyway
+        app.add_plugins(MaterialPlugin::<InkMaterial>::default())
+            .add_systems(PostUpdate, Plot::build);
+    }
+}
+
+/// The [`InkLine`] extension on top of a [`StandardMaterial`] whose base color is the ink
+pub type InkMaterial = ExtendedMaterial<StandardMaterial, InkLine>;
+
+/// Antialiased lines drawn along ribbon meshes, as built for a [`Plot`]
+#[derive(Asset, TypePath, AsBindGroup, Debug, Clone)]
+pub struct InkLine {
+    /// width of the line in world units
+    #[uniform(300)]
+    pub width: f32,
+
+    /// length of the dashes and of the gaps between them along uv.x, which counts cells
+    /// along the curve of a [`Plot`]. a length of 0 draws a solid line
+    #[uniform(301)]
+    pub dash: Vec2,
+}
+
+impl MaterialExtension for InkLine {
+    fn vertex_shader() -> ShaderRef {
+        "ink_line.wgsl".into()
+    }
+
+    fn fragment_shader() -> ShaderRef {
+        "ink_line.wgsl".into()
+    }
+}
+
+/// The shape drawn by a [`Plot`], in grid units
+#[derive(Clone)]
+pub enum PlotCurve {
+    /// y = f(x)
+    Function(Arc<dyn Fn(f32) -> f32 + Send + Sync>),
+
+    /// (x, y) = f(t)
+    Parametric(Arc<dyn Fn(f32) -> Vec2 + Send + Sync>),
+}
+
+/// A curve inked onto the grid surface described by the [`GridLayout`]
+///
+/// The curve is measured in grid cells from the grid origin. It is sampled evenly over its
+/// range and broken wherever it leaves the clip rectangle or the grid, or returns a value
+/// that isn't finite, so asymptotes don't draw a line straight across the sheet.
+///
+/// The entity gets the ink's mesh and material the first time it is built and is rebuilt
+/// whenever the plot or the layout change. The mesh is laid out in world space, so the
+/// entity's transform should stay at identity.
+#[derive(Component, Clone)]
+pub struct Plot {
+    pub curve: PlotCurve,
+
+    /// x values of a function, or t values of a parametric curve
+    pub range: Range<f32>,
+
+    /// number of segments the curve is split into
+    pub samples: u32,
+
+    pub color: Color,
+
+    /// width of the line in grid cells
+    pub thickness: f32,
+
+    /// part of the grid the curve may be drawn on, in grid cells
+    pub clip: Rect,
+
+    /// length of the dashes and of the gaps between them in grid cells, 0 draws a solid line
+    pub dash: Vec2,
+}
+
+impl Plot {
+    fn new(curve: PlotCurve, range: Range<f32>) -> Self {
+        Self {
+            curve,
+            range,
+            samples: 256,
+            color: Color::rgba(0.1, 0.1, 0.25, 1.0),
+            thickness: 0.1,
+            clip: Rect {
+                min: Vec2::splat(f32::NEG_INFINITY),
+                max: Vec2::splat(f32::INFINITY),
+            },
+            dash: Vec2::ZERO,
+        }
+    }
+
+    /// plots y = f(x) for x in range
+    pub fn function(f: impl Fn(f32) -> f32 + Send + Sync + 'static, range: Range<f32>) -> Self {
+        Self::new(PlotCurve::Function(Arc::new(f)), range)
+    }
+
+    /// plots the points f(t) for t in range
+    pub fn parametric(f: impl Fn(f32) -> Vec2 + Send + Sync + 'static, range: Range<f32>) -> Self {
+        Self::new(PlotCurve::Parametric(Arc::new(f)), range)
+    }
+
+    /// plots straight lines from point to point, with a sample on every point
+    pub fn polyline(points: Vec<Vec2>) -> Self {
+        let segments = points.len().saturating_sub(1);
+        Self::parametric(
+            move |t| {
+                let index = (t.max(0.0) as usize).min(segments.saturating_sub(1));
+                // without two points there is nothing to draw, which NaN takes care of
+                let start = points.get(index).copied().unwrap_or(Vec2::NAN);
+                let end = points.get(index + 1).copied().unwrap_or(Vec2::NAN);
+                start.lerp(end, t - index as f32)
+            },
+            0.0..segments as f32,
+        )
+        .with_samples(segments.max(1) as u32)
+    }
+
+    pub fn with_color(mut self, color: Color) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_thickness(mut self, thickness: f32) -> Self {
+        self.thickness = thickness;
+        self
+    }
+
+    pub fn with_samples(mut self, samples: u32) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    pub fn with_clip(mut self, clip: Rect) -> Self {
+        self.clip = clip;
+        self
+    }
+
+    pub fn with_dash(mut self, length: f32, gap: f32) -> Self {
+        self.dash = Vec2::new(length, gap);
+        self
+    }
+
+    /// point of the curve at parameter t, in grid cells
+    fn point(&self, t: f32) -> Vec2 {
+        match &self.curve {
+            PlotCurve::Function(f) => Vec2::new(t, f(t)),
+            PlotCurve::Parametric(f) => f(t),
+        }
+    }
+
+    /// Unbroken runs of the curve that lie inside clip
+    fn runs(&self, clip: Rect) -> Vec<Vec<Vec2>> {
+        let samples = self.samples.max(1);
+        let points: Vec<Vec2> = (0..=samples)
+            .map(|i| {
+                let t = i as f32 / samples as f32;
+                self.point(self.range.start + (self.range.end - self.range.start) * t)
+            })
+            .collect();
+
+        let mut runs: Vec<Vec<Vec2>> = Vec::new();
+        let mut run: Vec<Vec2> = Vec::new();
+        for segment in points.windows(2) {
+            let clipped = (segment[0].is_finite() && segment[1].is_finite())
+                .then(|| clip_segment(segment[0], segment[1], clip))
+                .flatten();
+
+            // a gap, or a segment that had its start clipped off, ends the current run
+            let continues = clipped.is_some_and(|(start, _)| {
+                run.last().is_some_and(|last| last.distance(start) <= 1e-4)
+            });
+            if !continues {
+                if run.len() > 1 {
+                    runs.push(std::mem::take(&mut run));
+                }
+                run.clear();
+            }
+
+            if let Some((start, end)) = clipped {
+                if run.is_empty() {
+                    run.push(start);
+                }
+                if run.last().is_some_and(|last| last.distance(end) > 1e-6) {
+                    run.push(end);
+                }
+            }
+        }
+        if run.len() > 1 {
+            runs.push(run);
+        }
+        runs
+    }
+
+    /// Ribbon mesh following the curve over the grid surface
+    pub fn mesh(&self, layout: &GridLayout) -> Mesh {
+        let bounds = Rect {
+            min: layout.bounds.min.as_vec2(),
+            max: (layout.bounds.max + IVec2::ONE).as_vec2(),
+        };
+        let normal = layout.normal();
+        // lift the ink off the paper, just enough to win the depth test
+        let lift = normal * layout.cell_size.min_element() * 0.01;
+
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut uvs: Vec<[f32; 2]> = Vec::new();
+        let mut tangents: Vec<[f32; 4]> = Vec::new();
+        let mut indices: Vec<u32> = Vec::new();
+
+        for run in self.runs(self.clip.intersect(bounds)) {
+            let world: Vec<Vec3> = run
+                .iter()
+                .map(|point| layout.grid_to_world(*point) + lift)
+                .collect();
+            // directions across each segment, lying in the grid surface
+            let across: Vec<Vec3> = world
+                .windows(2)
+                .map(|segment| normal.cross(segment[1] - segment[0]).normalize_or_zero())
+                .collect();
+
+            let index_offset = positions.len() as u32;
+            let mut distance = 0.0;
+            for (i, position) in world.iter().enumerate() {
+                let before = across[i.saturating_sub(1)];
+                let after = across[i.min(across.len() - 1)];
+                // miter the join, long enough to keep the width on both sides up to a point
+                let join = (before + after).normalize_or_zero();
+                let join = if join == Vec3::ZERO {
+                    after
+                } else {
+                    join / join.dot(after).max(0.25)
+                };
+
+                if i > 0 {
+                    distance += run[i].distance(run[i - 1]);
+                }
+                for side in [-1.0, 1.0] {
+                    positions.push(position.to_array());
+                    uvs.push([distance, side]);
+                    tangents.push([join.x, join.y, join.z, 1.0]);
+                }
+            }
+
+            for i in 0..world.len() as u32 - 1 {
+                let quad = index_offset + i * 2;
+                indices.extend([quad, quad + 2, quad + 1, quad + 1, quad + 2, quad + 3]);
+            }
+        }
+
+        let normals = vec![normal.to_array(); positions.len()];
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_indices(Some(Indices::U32(indices)))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    }
+
+    /// Ink material drawing the plot's line on a grid with the given layout
+    pub fn material(&self, layout: &GridLayout) -> InkMaterial {
+        InkMaterial {
+            base: StandardMaterial {
+                base_color: self.color,
+                alpha_mode: AlphaMode::Blend,
+                perceptual_roughness: 0.8,
+                // the ribbon is flat, so it can be seen from under a tilted sheet too
+                cull_mode: None,
+                double_sided: true,
+                ..Default::default()
+            },
+            extension: InkLine {
+                width: self.thickness * layout.cell_size.min_element(),
+                dash: self.dash,
+            },
+        }
+    }
+
+    /// Builds the ink of new and changed plots, and of every plot when the layout changes
+    #[allow(clippy::type_complexity)]
+    pub fn build(
+        mut commands: Commands,
+        plot_query: Query<(
+            Entity,
+            Ref<Plot>,
+            Option<&Handle<Mesh>>,
+            Option<&Handle<InkMaterial>>,
+        )>,
+        layout: Res<GridLayout>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<InkMaterial>>,
+    ) {
+        for (entity, plot, mesh, material) in plot_query.iter() {
+            if !plot.is_changed() && !layout.is_changed() {
+                continue;
+            }
+
+            let new_mesh = plot.mesh(&layout);
+            let new_material = plot.material(&layout);
+            if let Some((mesh, material)) = mesh.zip(material) {
+                meshes.insert(mesh, new_mesh);
+                materials.insert(material, new_material);
+                continue;
+            }
+
+            commands.entity(entity).insert((
+                MaterialMeshBundle {
+                    mesh: meshes.add(new_mesh),
+                    material: materials.add(new_material),
+                    ..Default::default()
+                },
+                NotShadowCaster,
+            ));
+        }
+    }
+}
+
+/// Part of the segment from a to b inside rect, if any
+fn clip_segment(a: Vec2, b: Vec2, rect: Rect) -> Option<(Vec2, Vec2)> {
+    let delta = b - a;
+    let (mut enter, mut exit) = (0.0_f32, 1.0_f32);
+    for axis in 0..2 {
+        if delta[axis] == 0.0 {
+            if a[axis] < rect.min[axis] || a[axis] > rect.max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let t0 = (rect.min[axis] - a[axis]) / delta[axis];
+        let t1 = (rect.max[axis] - a[axis]) / delta[axis];
+        enter = enter.max(t0.min(t1));
+        exit = exit.min(t0.max(t1));
+    }
+
+    (enter <= exit).then(|| (a + delta * enter, a + delta * exit))
+}
+
+/// A surface z = f(x, y) sampled over a rectangle of the xy plane
+///
+/// Plot x runs along the local x axis of the mesh, plot y along its local z axis and z rises
+/// along local y, the same way a [`GridLayout`] lays out its cells, and a plot unit is one
+/// local unit. Scale the entity to size the plot. The uvs are the plot's x and y, so a
+/// [`crate::grid::GridMaterial`] draws a cell per unit with its axes through the plot's origin,
+/// and a [`crate::grid::GridHeight`] taken from the entity's transform colors and contours it
+/// by z. Values that aren't finite are drawn at z = 0.
+#[derive(Clone)]
+pub struct SurfacePlot {
+    pub function: Arc<dyn Fn(Vec2) -> f32 + Send + Sync>,
+
+    /// part of the xy plane the surface covers
+    pub domain: Rect,
+
+    /// number of quads along x and y
+    pub resolution: UVec2,
+}
+
+impl SurfacePlot {
+    pub fn new(function: impl Fn(Vec2) -> f32 + Send + Sync + 'static, domain: Rect) -> Self {
+        Self {
+            function: Arc::new(function),
+            domain,
+            resolution: UVec2::new(64, 64),
+        }
+    }
+
+    pub fn with_resolution(mut self, resolution: UVec2) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    /// z at a point of the plot, 0 where the function isn't finite
+    pub fn z_at(&self, point: Vec2) -> f32 {
+        let z = (self.function)(point);
+        if z.is_finite() {
+            z
+        } else {
+            0.0
+        }
+    }
+
+    /// point of the plot at a vertex of the mesh, clamped to the domain
+    fn vertex_point(&self, vertex: IVec2) -> Vec2 {
+        let resolution = self.resolution.max(UVec2::ONE);
+        let vertex = vertex.clamp(IVec2::ZERO, resolution.as_ivec2());
+        self.domain.min + self.domain.size() * vertex.as_vec2() / resolution.as_vec2()
+    }
+
+    fn vertex_position(&self, vertex: IVec2) -> Vec3 {
+        let point = self.vertex_point(vertex);
+        Vec3::new(point.x, self.z_at(point), point.y)
+    }
+}
+
+impl From<SurfacePlot> for Mesh {
+    fn from(value: SurfacePlot) -> Self {
+        let resolution = value.resolution.max(UVec2::ONE);
+        let x_vertex_count = resolution.x + 1;
+        let z_vertex_count = resolution.y + 1;
+        let num_vertices = (x_vertex_count * z_vertex_count) as usize;
+        let num_indices = (resolution.x * resolution.y * 6) as usize;
+
+        let mut positions: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
+        let mut normals: Vec<[f32; 3]> = Vec::with_capacity(num_vertices);
+        let mut uvs: Vec<[f32; 2]> = Vec::with_capacity(num_vertices);
+        let mut tangents: Vec<[f32; 4]> = Vec::with_capacity(num_vertices);
+        let mut indices: Vec<u32> = Vec::with_capacity(num_indices);
+
+        for z in 0..z_vertex_count as i32 {
+            for x in 0..x_vertex_count as i32 {
+                let vertex = IVec2::new(x, z);
+                // central differences like GridTerrain, one sided along the edges
+                let along_x = value.vertex_position(vertex + IVec2::X)
+                    - value.vertex_position(vertex - IVec2::X);
+                let along_z = value.vertex_position(vertex + IVec2::Y)
+                    - value.vertex_position(vertex - IVec2::Y);
+                let normal = along_z.cross(along_x).normalize_or_zero();
+                let tangent = along_x.normalize_or_zero();
+
+                positions.push(value.vertex_position(vertex).to_array());
+                normals.push(normal.to_array());
+                uvs.push(value.vertex_point(vertex).to_array());
+                tangents.push([tangent.x, tangent.y, tangent.z, 1.0]);
+            }
+        }
+
+        for z in 0..z_vertex_count - 1 {
+            for x in 0..x_vertex_count - 1 {
+                let quad = z * x_vertex_count + x;
+                indices.push(quad + x_vertex_count + 1);
+                indices.push(quad + 1);
+                indices.push(quad + x_vertex_count);
+                indices.push(quad);
+                indices.push(quad + x_vertex_count);
+                indices.push(quad + 1);
+            }
+        }
+
+        Mesh::new(PrimitiveTopology::TriangleList)
+            .with_indices(Some(Indices::U32(indices)))
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_TANGENT, tangents)
+    }
+}